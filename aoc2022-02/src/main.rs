@@ -1,74 +1,183 @@
+use anyhow::{bail, Context};
+use itertools::Itertools;
 use std::{
     cmp::Ordering,
+    collections::HashMap,
     error::Error,
-    fs::File,
+    fs::{read_to_string, File},
     io::{BufRead, BufReader},
+    str::FromStr,
 };
 
 const SCORE_LOST: u32 = 0;
 const SCORE_DRAW: u32 = 3;
 const SCORE_WIN: u32 = 6;
 
-#[derive(PartialEq, Eq, Ord, Debug, Clone)]
-enum HandSign {
-    ROCK,
-    PAPER,
-    SCISSOR,
+// the classic game is used whenever no rules file is given
+const DEFAULT_RULES: &str = include_str!("../rps.rules");
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+struct HandSign {
+    index: usize,
+    name: String,
+    score: u32,
+    // bitmask of the indices of all signs this one beats
+    beats: u64,
 }
 
 impl HandSign {
     pub fn to_score(&self) -> u32 {
-        match self {
-            Self::ROCK => 1,
-            Self::PAPER => 2,
-            Self::SCISSOR => 3,
-        }
+        self.score
     }
-}
-
-impl TryFrom<&str> for HandSign {
-    type Error = anyhow::Error;
 
-    fn try_from(value: &str) -> Result<Self, Self::Error> {
-        match value {
-            "A" | "X" => Ok(Self::ROCK),
-            "B" | "Y" => Ok(Self::PAPER),
-            "C" | "Z" => Ok(Self::SCISSOR),
-            _ => Err(anyhow::Error::msg(format!(
-                "Failed to parse '{}' into handsign!",
-                value
-            ))),
-        }
+    fn beats(&self, other: &Self) -> bool {
+        self.beats & (1 << other.index) != 0
     }
 }
 
 impl PartialOrd for HandSign {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        if self == other {
+        if self.index == other.index {
             Some(Ordering::Equal)
-        } else if self == &Self::ROCK && other == &Self::PAPER {
-            Some(Ordering::Less)
-        } else if self == &Self::PAPER && other == &Self::SCISSOR {
-            Some(Ordering::Less)
-        } else if self == &Self::SCISSOR && other == &Self::ROCK {
-            Some(Ordering::Less)
-        } else if self == &Self::PAPER && other == &Self::ROCK {
-            Some(Ordering::Greater)
-        } else if self == &Self::ROCK && other == &Self::SCISSOR {
-            Some(Ordering::Greater)
-        } else if self == &Self::SCISSOR && other == &Self::PAPER {
+        } else if self.beats(other) {
             Some(Ordering::Greater)
+        } else if other.beats(self) {
+            Some(Ordering::Less)
         } else {
             None
         }
     }
 }
 
+#[derive(Debug)]
+struct Game {
+    signs: Vec<HandSign>,
+    codes: HashMap<String, usize>,
+}
+
+impl Game {
+    fn handsign(&self, code: &str) -> Result<&HandSign, anyhow::Error> {
+        self.codes
+            .get(code)
+            .map(|idx| &self.signs[*idx])
+            .ok_or_else(|| anyhow::Error::msg(format!("Failed to parse '{code}' into handsign!")))
+    }
+
+    // the response scoring best against a known opponent sign
+    fn best_response(&self, opponent: &HandSign) -> &HandSign {
+        self.signs
+            .iter()
+            .rev()
+            .max_by_key(|me| score_round(opponent, me))
+            .expect("a validated game has signs")
+    }
+
+    fn find_by_name(&self, name: &str) -> Result<usize, anyhow::Error> {
+        self.signs
+            .iter()
+            .position(|sign| sign.name == name)
+            .with_context(|| format!("Unknown sign '{name}'"))
+    }
+
+    fn validate(&self) -> Result<(), anyhow::Error> {
+        let n = self.signs.len();
+        if n < 3 || n.is_multiple_of(2) {
+            bail!("A cyclic game needs an odd number of at least 3 signs, got {n}");
+        }
+
+        for (a, b) in self.signs.iter().tuple_combinations() {
+            match (a.beats(b), b.beats(a)) {
+                (true, true) => bail!("'{}' and '{}' beat each other", a.name, b.name),
+                (false, false) => bail!(
+                    "Neither '{}' beats '{}' nor the other way round",
+                    a.name,
+                    b.name
+                ),
+                _ => {}
+            }
+        }
+
+        for sign in self.signs.iter() {
+            if sign.beats(sign) {
+                bail!("'{}' beats itself", sign.name);
+            }
+            // in a fair cyclic game every sign beats exactly half of the others
+            let wins = sign.beats.count_ones() as usize;
+            if wins != (n - 1) / 2 {
+                bail!(
+                    "'{}' beats {wins} signs, but every sign has to beat exactly {}",
+                    sign.name,
+                    (n - 1) / 2
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for Game {
+    type Err = anyhow::Error;
+
+    fn from_str(rules: &str) -> Result<Self, Self::Err> {
+        let mut game = Self {
+            signs: Vec::new(),
+            codes: HashMap::new(),
+        };
+
+        for (line_number, line) in rules.lines().enumerate() {
+            let line_number = line_number + 1;
+            let line = line.split_once('#').map_or(line, |(rule, _)| rule);
+            let words = line.split_whitespace().collect::<Vec<_>>();
+            match words.as_slice() {
+                [] => {}
+                ["sign", name, score, codes @ ..] => {
+                    if game.find_by_name(name).is_ok() {
+                        bail!("Line {line_number}: Sign '{name}' is defined twice");
+                    }
+                    if game.signs.len() >= 64 {
+                        bail!("Line {line_number}: A game supports at most 64 signs");
+                    }
+                    let index = game.signs.len();
+                    for code in codes.iter() {
+                        if game.codes.insert(code.to_string(), index).is_some() {
+                            bail!("Line {line_number}: Code '{code}' is used twice");
+                        }
+                    }
+                    game.signs.push(HandSign {
+                        index,
+                        name: name.to_string(),
+                        score: score.parse().with_context(|| {
+                            format!("Line {line_number}: Invalid score '{score}'")
+                        })?,
+                        beats: 0,
+                    });
+                }
+                [winner, "beats", losers @ ..] if !losers.is_empty() => {
+                    let winner = game
+                        .find_by_name(winner)
+                        .with_context(|| format!("Line {line_number}"))?;
+                    for loser in losers.iter() {
+                        let loser = game
+                            .find_by_name(loser)
+                            .with_context(|| format!("Line {line_number}"))?;
+                        game.signs[winner].beats |= 1 << loser;
+                    }
+                }
+                _ => bail!("Line {line_number}: Failed to parse rule '{line}'"),
+            }
+        }
+
+        game.validate()?;
+        Ok(game)
+    }
+}
+
 #[derive(Debug, PartialEq, Eq)]
 enum WinningInstructions {
-    LOSE,
-    DRAW,
-    WIN,
+    Lose,
+    Draw,
+    Win,
 }
 
 impl TryFrom<&str> for WinningInstructions {
@@ -76,9 +185,9 @@ impl TryFrom<&str> for WinningInstructions {
 
     fn try_from(value: &str) -> Result<Self, Self::Error> {
         match value {
-            "X" => Ok(Self::LOSE),
-            "Y" => Ok(Self::DRAW),
-            "Z" => Ok(Self::WIN),
+            "X" => Ok(Self::Lose),
+            "Y" => Ok(Self::Draw),
+            "Z" => Ok(Self::Win),
             _ => Err(anyhow::Error::msg(format!(
                 "Failed to parse '{}' into winning instruction!",
                 value
@@ -88,57 +197,262 @@ impl TryFrom<&str> for WinningInstructions {
 }
 
 impl WinningInstructions {
-    pub fn to_handsign(&self, opponent: &HandSign) -> HandSign {
+    // with more than three signs several signs reach the outcome, so pick the one scoring best
+    pub fn to_handsign(&self, game: &Game, opponent: &HandSign) -> Result<HandSign, anyhow::Error> {
+        let outcome = match self {
+            Self::Lose => Ordering::Less,
+            Self::Draw => Ordering::Equal,
+            Self::Win => Ordering::Greater,
+        };
+        game.signs
+            .iter()
+            .rev()
+            .filter(|sign| (*sign).partial_cmp(opponent) == Some(outcome))
+            .max_by_key(|sign| sign.to_score())
+            .cloned()
+            .with_context(|| format!("No sign reaches {self:?} against {}", opponent.name))
+    }
+}
+
+trait Strategy {
+    fn name(&self) -> &'static str;
+
+    // decides which hand sign to play, given the second column of the strategy guide
+    fn choose_handsign(
+        &self,
+        game: &Game,
+        opponent: &HandSign,
+        column: &str,
+    ) -> Result<HandSign, anyhow::Error>;
+}
+
+// first interpretation: X, Y and Z are the hand signs to play
+struct ShapeStrategy;
+
+impl Strategy for ShapeStrategy {
+    fn name(&self) -> &'static str {
+        "shape"
+    }
+
+    fn choose_handsign(
+        &self,
+        game: &Game,
+        _opponent: &HandSign,
+        column: &str,
+    ) -> Result<HandSign, anyhow::Error> {
+        game.handsign(column).cloned()
+    }
+}
+
+// second interpretation: X, Y and Z tell whether to lose, draw or win
+struct OutcomeStrategy;
+
+impl Strategy for OutcomeStrategy {
+    fn name(&self) -> &'static str {
+        "outcome"
+    }
+
+    fn choose_handsign(
+        &self,
+        game: &Game,
+        opponent: &HandSign,
+        column: &str,
+    ) -> Result<HandSign, anyhow::Error> {
+        WinningInstructions::try_from(column)?.to_handsign(game, opponent)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum StrategySelection {
+    Shape,
+    Outcome,
+    Both,
+}
+
+impl StrategySelection {
+    fn strategies(&self) -> Vec<Box<dyn Strategy>> {
         match self {
-            Self::DRAW => opponent.clone(),
-            Self::LOSE => match opponent {
-                HandSign::ROCK => HandSign::SCISSOR,
-                HandSign::PAPER => HandSign::ROCK,
-                HandSign::SCISSOR => HandSign::PAPER,
-            },
-            Self::WIN => match opponent {
-                HandSign::ROCK => HandSign::PAPER,
-                HandSign::PAPER => HandSign::SCISSOR,
-                HandSign::SCISSOR => HandSign::ROCK,
-            },
+            Self::Shape => vec![Box::new(ShapeStrategy)],
+            Self::Outcome => vec![Box::new(OutcomeStrategy)],
+            Self::Both => vec![Box::new(ShapeStrategy), Box::new(OutcomeStrategy)],
+        }
+    }
+}
+
+impl FromStr for StrategySelection {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "shape" => Ok(Self::Shape),
+            "outcome" => Ok(Self::Outcome),
+            "both" => Ok(Self::Both),
+            _ => bail!("Unknown strategy '{s}', expected 'shape', 'outcome' or 'both'"),
         }
     }
 }
 
+#[derive(Debug)]
+struct Options {
+    file_name: String,
+    strategy: StrategySelection,
+    rules: Option<String>,
+    analyse: bool,
+}
+
+impl Options {
+    fn from_args() -> Result<Self, anyhow::Error> {
+        let mut file_name: Option<String> = None;
+        let mut strategy = StrategySelection::Both;
+        let mut rules: Option<String> = None;
+        let mut analyse = false;
+
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--strategy" => {
+                    strategy = args
+                        .next()
+                        .context("--strategy requires one of 'shape', 'outcome' or 'both'")?
+                        .parse()?
+                }
+                "--rules" => rules = Some(args.next().context("--rules requires a file")?),
+                "--analyse" => analyse = true,
+                flag if flag.starts_with("--") => bail!("Unknown option '{flag}'"),
+                _ if file_name.is_none() => file_name = Some(arg),
+                _ => bail!("Unexpected argument '{arg}'"),
+            }
+        }
+
+        Ok(Self {
+            file_name: file_name.context("No input file supplied!")?,
+            strategy,
+            rules,
+            analyse,
+        })
+    }
+}
+
+fn score_round(opponent: &HandSign, me: &HandSign) -> u32 {
+    let outcome = if me > opponent {
+        SCORE_WIN
+    } else if me < opponent {
+        SCORE_LOST
+    } else {
+        SCORE_DRAW
+    };
+    me.to_score() + outcome
+}
+
+fn print_analysis(
+    game: &Game,
+    opponent_counts: &[u32],
+    strategies: &[Box<dyn Strategy>],
+    total_scores: &[u32],
+) {
+    let rounds: u32 = opponent_counts.iter().sum();
+    if rounds == 0 {
+        println!("No rounds to analyse");
+        return;
+    }
+    let share = |count: u32| f64::from(count) / f64::from(rounds);
+
+    println!();
+    println!("Opponent distribution over {rounds} rounds:");
+    println!(
+        "  {:<10} {:>6} {:>7}  {:<10} {:>5}",
+        "sign", "count", "share", "response", "score"
+    );
+    let mut optimal_score: u32 = 0;
+    for (opponent, count) in game.signs.iter().zip(opponent_counts.iter()) {
+        let response = game.best_response(opponent);
+        let score = score_round(opponent, response);
+        optimal_score += score * count;
+        println!(
+            "  {:<10} {:>6} {:>6.1}%  {:<10} {:>5}",
+            opponent.name,
+            count,
+            share(*count) * 100.0,
+            response.name,
+            score
+        );
+    }
+
+    // without knowing the next opponent sign, the best we can do is to always play the same sign
+    let expected_score = |me: &HandSign| -> f64 {
+        game.signs
+            .iter()
+            .zip(opponent_counts.iter())
+            .map(|(opponent, count)| share(*count) * f64::from(score_round(opponent, me)))
+            .sum()
+    };
+    if let Some((fixed_response, expected)) = game
+        .signs
+        .iter()
+        .map(|me| (me, expected_score(me)))
+        .rev()
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+    {
+        println!(
+            "Best fixed response to this distribution: {} (expected {:.2} per round)",
+            fixed_response.name, expected
+        );
+    }
+
+    println!();
+    println!(
+        "Optimum when knowing every opponent sign: {optimal_score} ({:.2} per round)",
+        f64::from(optimal_score) / f64::from(rounds)
+    );
+    for (strategy, total_score) in strategies.iter().zip(total_scores.iter()) {
+        println!(
+            "{:>8}: {} ({:.2} per round), {} below the optimum",
+            strategy.name(),
+            total_score,
+            f64::from(*total_score) / f64::from(rounds),
+            optimal_score - total_score
+        );
+    }
+}
+
 fn main() -> Result<(), Box<dyn Error>> {
-    let file_name = std::env::args().nth(1).expect("No input file supplied!");
-    let reader = BufReader::new(File::open(file_name)?);
-
-    let mut total_score: u32 = 0;
-    for line in reader.lines() {
-        let (opponent, me): (HandSign, WinningInstructions) = {
-            let line = line?;
-            let mut split = line.splitn(2, ' ');
-            (
-                split
-                    .next()
-                    .ok_or_else(|| anyhow::Error::msg("Insufficient elements"))?
-                    .try_into()?,
-                split
-                    .next()
-                    .ok_or_else(|| anyhow::Error::msg("Insufficient elements"))?
-                    .try_into()?,
-            )
-        };
-        let me = me.to_handsign(&opponent);
+    let options = Options::from_args()?;
+    let game: Game = match &options.rules {
+        Some(rules_file) => read_to_string(rules_file)?
+            .parse()
+            .with_context(|| format!("Invalid rules in '{rules_file}'"))?,
+        None => DEFAULT_RULES.parse()?,
+    };
+    let reader = BufReader::new(File::open(&options.file_name)?);
 
-        let mut score: u32 = me.to_score();
-        if me > opponent {
-            score += SCORE_WIN;
-        } else if me < opponent {
-            score += SCORE_LOST;
-        } else {
-            score += SCORE_DRAW;
+    let strategies = options.strategy.strategies();
+    let mut total_scores: Vec<u32> = vec![0; strategies.len()];
+    let mut opponent_counts: Vec<u32> = vec![0; game.signs.len()];
+    for (line_number, line) in reader.lines().enumerate() {
+        let line = line?;
+        let (opponent, column) = line
+            .split_once(' ')
+            .with_context(|| format!("Line {}: Insufficient elements", line_number + 1))?;
+        let opponent = game
+            .handsign(opponent)
+            .with_context(|| format!("Line {}", line_number + 1))?;
+        opponent_counts[opponent.index] += 1;
+
+        for (strategy, total_score) in strategies.iter().zip(total_scores.iter_mut()) {
+            let me = strategy
+                .choose_handsign(&game, opponent, column)
+                .with_context(|| format!("Line {}", line_number + 1))?;
+            *total_score += score_round(opponent, &me);
         }
-        total_score += score;
-        println!("{:?} {:?} {:?} {}", opponent, me, me > opponent, score);
     }
-    println!("{}", total_score);
+
+    for (strategy, total_score) in strategies.iter().zip(total_scores.iter()) {
+        println!("{:>8}: {}", strategy.name(), total_score);
+    }
+
+    if options.analyse {
+        print_analysis(&game, &opponent_counts, &strategies, &total_scores);
+    }
 
     Ok(())
 }
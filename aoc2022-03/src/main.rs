@@ -1,87 +1,237 @@
+use anyhow::{bail, Context};
 use itertools::Itertools;
 use std::{
-    collections::HashSet,
     error::Error,
     fs::File,
     io::{BufRead, BufReader},
+    str::FromStr,
 };
 
-// #[derive(Debug)]
-// struct Rucksack {
-//     pub comp1: HashSet<char>,
-//     pub comp2: HashSet<char>,
-// }
-
-// impl Rucksack {
-//     pub fn new(line: String) -> Self {
-//         let (comp1, comp2) = line.split_at(line.len() / 2);
-//         Rucksack {
-//             comp1: HashSet::from_iter(comp1.chars()),
-//             comp2: HashSet::from_iter(comp2.chars()),
-//         }
-//     }
-// }
-
-fn score_from_char(input: &char) -> u32 {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Compartments,
+    Badges,
+    Both,
+}
+
+impl FromStr for Mode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "compartments" => Ok(Self::Compartments),
+            "badges" => Ok(Self::Badges),
+            "both" => Ok(Self::Both),
+            _ => bail!("Unknown mode '{s}', expected 'compartments', 'badges' or 'both'"),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Options {
+    file_name: String,
+    mode: Mode,
+    group_size: usize,
+}
+
+impl Options {
+    fn from_args() -> Result<Self, anyhow::Error> {
+        let mut file_name: Option<String> = None;
+        let mut mode = Mode::Both;
+        let mut group_size: usize = 3;
+
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--mode" => {
+                    mode = args
+                        .next()
+                        .context("--mode requires one of 'compartments', 'badges' or 'both'")?
+                        .parse()?
+                }
+                "--group-size" => {
+                    group_size = args
+                        .next()
+                        .context("--group-size requires a number")?
+                        .parse()
+                        .context("--group-size requires a number")?
+                }
+                flag if flag.starts_with("--") => bail!("Unknown option '{flag}'"),
+                _ if file_name.is_none() => file_name = Some(arg),
+                _ => bail!("Unexpected argument '{arg}'"),
+            }
+        }
+
+        Ok(Self {
+            file_name: file_name.context("No input file supplied!")?,
+            mode,
+            group_size,
+        })
+    }
+}
+
+fn score_from_char(input: char) -> Option<u32> {
     if input.is_ascii_lowercase() {
-        (*input as u32) - 96
+        Some((input as u32) - 96)
     } else if input.is_ascii_uppercase() {
-        (*input as u32) - 64 + 26
+        Some((input as u32) - 64 + 26)
     } else {
-        panic!("'{}' is not an ascii character!", input);
+        None
     }
 }
 
-fn div_floor(a: usize, b: u32) -> u32 {
-    ((a as f64) / f64::from(b)).floor() as u32
+fn char_from_score(score: u32) -> char {
+    match score {
+        1..=26 => char::from_u32(score + 96).unwrap_or('?'),
+        27..=52 => char::from_u32(score - 26 + 64).unwrap_or('?'),
+        _ => '?',
+    }
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let file_name = std::env::args().nth(1).expect("No input file supplied!");
-    let reader = BufReader::new(File::open(file_name)?);
+// every item type sets the bit of its priority, all 52 priorities fit into an u64
+fn item_mask(items: &str) -> Result<u64, anyhow::Error> {
+    items.chars().try_fold(0u64, |mask, item| {
+        score_from_char(item)
+            .map(|score| mask | (1 << score))
+            .with_context(|| format!("'{item}' is not an ascii letter!"))
+    })
+}
 
-    let groups = reader
-        .lines()
-        .into_iter()
-        .map(|line| HashSet::<char>::from_iter(line.unwrap().chars()))
-        .enumerate()
-        .group_by(|(idx, _)| div_floor(*idx, 3));
+// the item with the lowest priority in the mask
+fn first_item(mask: u64) -> Option<u32> {
+    if mask == 0 {
+        None
+    } else {
+        Some(mask.trailing_zeros())
+    }
+}
+
+#[derive(Debug)]
+struct Rucksack {
+    line_number: usize,
+    items: u64,
+    // both halves, None for an odd number of items
+    compartments: Option<(u64, u64)>,
+}
+
+impl Rucksack {
+    fn parse(line_number: usize, line: &str) -> Result<Self, anyhow::Error> {
+        // validates all items first, so splitting by bytes below is safe
+        let items = item_mask(line)
+            .map_err(|err| anyhow::Error::msg(format!("Line {line_number}: {err}")))?;
+        let compartments = if line.len().is_multiple_of(2) {
+            let (comp1, comp2) = line.split_at(line.len() / 2);
+            Some((item_mask(comp1)?, item_mask(comp2)?))
+        } else {
+            None
+        };
+        Ok(Self {
+            line_number,
+            items,
+            compartments,
+        })
+    }
+
+    fn items(&self) -> u64 {
+        self.items
+    }
+}
+
+fn compartment_score(rucksacks: &[Rucksack]) -> Result<u32, anyhow::Error> {
+    rucksacks
+        .iter()
+        .map(|ruck| {
+            let (comp1, comp2) = ruck.compartments.with_context(|| {
+                format!(
+                    "Line {}: Odd number of items can not be split into two compartments",
+                    ruck.line_number
+                )
+            })?;
+            first_item(comp1 & comp2).with_context(|| {
+                format!(
+                    "Line {}: Compartments do not share any item",
+                    ruck.line_number
+                )
+            })
+        })
+        .sum()
+}
+
+fn items_in_mask(mask: u64) -> String {
+    (1..=52)
+        .filter(|score| mask & (1 << score) != 0)
+        .map(char_from_score)
+        .join(", ")
+}
+
+fn badge_score(rucksacks: &[Rucksack], group_size: usize) -> Result<u32, anyhow::Error> {
+    if group_size == 0 {
+        bail!("Group size has to be at least 1");
+    }
+    let incomplete = rucksacks.len() % group_size;
+    if incomplete != 0 {
+        let first = &rucksacks[rucksacks.len() - incomplete];
+        bail!(
+            "Last group starting at line {} only has {incomplete} of {group_size} rucksacks",
+            first.line_number
+        );
+    }
 
     let mut total_score: u32 = 0;
-    for (group, rucks) in &groups {
-        let badge = rucks
-            .map(|(_, ruck)| ruck)
-            .reduce(|accum, item| -> HashSet<char> {
-                accum.intersection(&item).copied().collect()
-            });
-        // println!("{}: {:?}", group, badge);
-        if let Some(badge) = badge {
-            let badge = badge
-                .iter()
-                .next()
-                .ok_or_else(|| anyhow::Error::msg("No badge for you!"))?;
-
-            let score = score_from_char(badge);
-            total_score += score;
-            println!("{} [{}] -> {}", group, badge, score);
+    let mut problems: Vec<String> = Vec::new();
+    for (group, rucks) in rucksacks.chunks(group_size).enumerate() {
+        let badges = rucks
+            .iter()
+            .fold(u64::MAX, |accum, ruck| accum & ruck.items());
+        let lines = format!(
+            "group {group} (lines {}-{})",
+            rucks[0].line_number,
+            rucks[rucks.len() - 1].line_number
+        );
+        match badges.count_ones() {
+            1 => {
+                let badge = badges.trailing_zeros();
+                total_score += badge;
+                println!("{} [{}] -> {}", group, char_from_score(badge), badge);
+            }
+            0 => problems.push(format!("  {lines}: no common item")),
+            count => problems.push(format!(
+                "  {lines}: {count} common items ({})",
+                items_in_mask(badges)
+            )),
         }
     }
 
-    // for line in reader.lines() {
-    //     let line = line?;
-    //     let (comp1, comp2) = line.split_at(line.len() / 2);
-    //     let comp1: HashSet<char> = HashSet::from_iter(comp1.chars());
-    //     let comp2: HashSet<char> = HashSet::from_iter(comp2.chars());
-
-    //     println!("{}:{:?} {}:{:?}", comp1.len(), comp1, comp2.len(), comp2);
-    //     if let Some(intersect) = comp1.intersection(&comp2).next() {
-    //         let score = score_from_char(intersect);
-    //         total_score += score;
-    //         println!(" -> {} {}", intersect, score);
-    //     }
-    // }
-
-    println!("==> {}", total_score);
+    if !problems.is_empty() {
+        bail!(
+            "{} of {} groups have no unique badge:\n{}",
+            problems.len(),
+            rucksacks.len() / group_size,
+            problems.join("\n")
+        );
+    }
+    Ok(total_score)
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let options = Options::from_args()?;
+    let reader = BufReader::new(File::open(&options.file_name)?);
+
+    let rucksacks = reader
+        .lines()
+        .enumerate()
+        .map(|(idx, line)| Rucksack::parse(idx + 1, &line?))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if options.mode != Mode::Badges {
+        println!("compartments ==> {}", compartment_score(&rucksacks)?);
+    }
+    if options.mode != Mode::Compartments {
+        println!(
+            "badges ==> {}",
+            badge_score(&rucksacks, options.group_size)?
+        );
+    }
 
     Ok(())
 }
@@ -1,49 +1,223 @@
-use anyhow::Context;
+use anyhow::{bail, Context};
 use std::{
-    collections::HashSet,
+    collections::HashMap,
     error::Error,
     fs::File,
     io::{BufRead, BufReader},
 };
 
-fn split_range(range: &str) -> Result<(u32, u32), anyhow::Error> {
+// inclusive range of section IDs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SectionRange {
+    from: u64,
+    to: u64,
+}
+
+impl SectionRange {
+    fn new(from: u64, to: u64) -> Result<Self, anyhow::Error> {
+        if from > to {
+            return Err(anyhow::Error::msg(format!(
+                "Range {from}-{to} ends before it starts"
+            )));
+        }
+        Ok(Self { from, to })
+    }
+
+    // saturates for the (unlikely) range covering every u64
+    fn len(&self) -> u64 {
+        (self.to - self.from).saturating_add(1)
+    }
+
+    fn contains(&self, other: &Self) -> bool {
+        self.from <= other.from && self.to >= other.to
+    }
+
+    fn overlaps(&self, other: &Self) -> bool {
+        self.from <= other.to && other.from <= self.to
+    }
+
+    fn intersection(&self, other: &Self) -> Option<Self> {
+        if self.overlaps(other) {
+            Some(Self {
+                from: self.from.max(other.from),
+                to: self.to.min(other.to),
+            })
+        } else {
+            None
+        }
+    }
+}
+
+fn split_range(range: &str) -> Result<SectionRange, anyhow::Error> {
     let (from, to) = range
         .split_once('-')
         .with_context(|| format!("Wrong format in range: {}", range))?;
 
-    Ok((from.parse()?, to.parse()?))
+    SectionRange::new(
+        from.parse()
+            .with_context(|| format!("Invalid section ID '{from}'"))?,
+        to.parse()
+            .with_context(|| format!("Invalid section ID '{to}'"))?,
+    )
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let file_name = std::env::args().nth(1).expect("No input file supplied!");
-    let reader = BufReader::new(File::open(file_name)?);
+fn parse_pair(line: &str) -> Result<(SectionRange, SectionRange), anyhow::Error> {
+    let (group1, group2) = line
+        .split_once(',')
+        .with_context(|| format!("Wrong format, no comma: {line}"))?;
+    Ok((split_range(group1)?, split_range(group2)?))
+}
 
-    let overlapping_pairs = reader
-        .lines()
-        .into_iter()
-        .filter_map(|l| l.ok())
-        .map(|l| {
-            let Some((group1, group2)) = l.split_once(',') else {
-            panic!("Wrong format, no comma: {}", l);
+// splits the covered span into maximal segments, each covered by the same number of elves
+fn coverage(ranges: &[SectionRange]) -> Vec<(SectionRange, usize)> {
+    // (position, change in coverage) with exclusive ends
+    let mut events: Vec<(u64, isize)> = Vec::with_capacity(ranges.len() * 2);
+    for range in ranges.iter() {
+        events.push((range.from, 1));
+        if let Some(end) = range.to.checked_add(1) {
+            events.push((end, -1));
+        }
+    }
+    events.sort_unstable();
+
+    let mut segments: Vec<(SectionRange, usize)> = Vec::new();
+    let mut count: isize = 0;
+    for (idx, (position, delta)) in events.iter().enumerate() {
+        count += delta;
+        let end = match events.get(idx + 1) {
+            Some((next, _)) if next > position => next - 1,
+            Some(_) => continue,
+            // only an open-ended range reaching u64::MAX is left
+            None if count > 0 => u64::MAX,
+            None => break,
+        };
+        let segment = SectionRange {
+            from: *position,
+            to: end,
         };
-            (split_range(group1).unwrap(), split_range(group2).unwrap())
+        match segments.last_mut() {
+            Some((last, last_count)) if *last_count == count as usize => last.to = segment.to,
+            _ => segments.push((segment, count as usize)),
+        }
+    }
+    segments
+}
+
+#[derive(Debug)]
+struct Options {
+    file_name: String,
+    max_cover: usize,
+}
+
+impl Options {
+    fn from_args() -> Result<Self, anyhow::Error> {
+        let mut file_name: Option<String> = None;
+        let mut max_cover: usize = 2;
+
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--max-cover" => {
+                    max_cover = args
+                        .next()
+                        .context("--max-cover requires a number")?
+                        .parse()
+                        .context("--max-cover requires a number")?
+                }
+                flag if flag.starts_with("--") => bail!("Unknown option '{flag}'"),
+                _ if file_name.is_none() => file_name = Some(arg),
+                _ => bail!("Unexpected argument '{arg}'"),
+            }
+        }
+
+        Ok(Self {
+            file_name: file_name.context("No input file supplied!")?,
+            max_cover,
         })
-        // // fully overlapping ranges
-        // .filter(|((g1_from, g1_to), (g2_from, g2_to))| {
-        //     // group 1 includes 2
-        //     g1_from <= g2_from && g1_to >= g2_to
-        // // group 2 includes 1
-        // || g2_from <= g1_from && g2_to >= g1_to
-        // })
-        // partly overlapping ranges
-        .filter(|((g1_from, g1_to), (g2_from, g2_to))| {
-            let g1: HashSet<u32> = HashSet::from_iter((*g1_from)..=(*g1_to));
-            let g2: HashSet<u32> = HashSet::from_iter((*g2_from)..=(*g2_to));
-            g1.intersection(&g2).count() > 0
+    }
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let options = Options::from_args()?;
+    let reader = BufReader::new(File::open(&options.file_name)?);
+
+    let pairs = reader
+        .lines()
+        .enumerate()
+        .map(|(idx, line)| {
+            parse_pair(&line?)
+                .map_err(|err| anyhow::Error::msg(format!("Line {}: {err:#}", idx + 1)))
         })
-        .count();
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut containing_pairs: u64 = 0;
+    let mut overlapping_pairs: u64 = 0;
+    let mut shared_sections: u64 = 0;
+    for (g1, g2) in pairs.iter() {
+        if g1.contains(g2) || g2.contains(g1) {
+            containing_pairs += 1;
+        }
+        if let Some(shared) = g1.intersection(g2) {
+            overlapping_pairs += 1;
+            shared_sections += shared.len();
+        }
+    }
 
+    println!("Pairs where one range fully contains the other: {containing_pairs}");
     println!("Pairs with overlapping ranges: {overlapping_pairs}");
+    println!("Sections assigned twice within a pair: {shared_sections}");
+
+    let ranges = pairs
+        .iter()
+        .flat_map(|(g1, g2)| [*g1, *g2])
+        .collect::<Vec<_>>();
+    let segments = coverage(&ranges);
+
+    println!();
+    println!("Sections covered by no elf:");
+    for (segment, _) in segments.iter().filter(|(_, count)| *count == 0) {
+        println!(
+            "  {}-{} ({} sections)",
+            segment.from,
+            segment.to,
+            segment.len()
+        );
+    }
+    println!("Sections covered by more than {} elves:", options.max_cover);
+    for (segment, count) in segments
+        .iter()
+        .filter(|(_, count)| *count > options.max_cover)
+    {
+        println!(
+            "  {}-{} ({} sections) by {count} elves",
+            segment.from,
+            segment.to,
+            segment.len()
+        );
+    }
+
+    // the order of the elves within a pair does not matter
+    let mut duplicates: HashMap<(u64, u64, u64, u64), Vec<usize>> = HashMap::new();
+    for (idx, (g1, g2)) in pairs.iter().enumerate() {
+        let (a, b) = if (g1.from, g1.to) <= (g2.from, g2.to) {
+            (g1, g2)
+        } else {
+            (g2, g1)
+        };
+        duplicates
+            .entry((a.from, a.to, b.from, b.to))
+            .or_default()
+            .push(idx + 1);
+    }
+    let mut duplicates = duplicates
+        .into_iter()
+        .filter(|(_, lines)| lines.len() > 1)
+        .collect::<Vec<_>>();
+    duplicates.sort_by_key(|(_, lines)| lines[0]);
+    println!("Duplicate pairs:");
+    for ((a_from, a_to, b_from, b_to), lines) in duplicates.iter() {
+        println!("  {a_from}-{a_to},{b_from}-{b_to} on lines {lines:?}");
+    }
 
     Ok(())
 }
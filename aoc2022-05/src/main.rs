@@ -1,13 +1,60 @@
-use anyhow::Context;
-use itertools::Itertools;
+use anyhow::{bail, Context};
+use itertools::{EitherOrBoth, Itertools};
 use std::{
     collections::VecDeque,
     error::Error,
+    fmt::Display,
     fs::File,
     io::{BufRead, BufReader},
+    str::FromStr,
+    thread::sleep,
+    time::Duration,
 };
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CraneModel {
+    CrateMover9000,
+    CrateMover9001,
+}
+
+impl CraneModel {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::CrateMover9000 => "CrateMover 9000",
+            Self::CrateMover9001 => "CrateMover 9001",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum CraneSelection {
+    Single(CraneModel),
+    Both,
+}
+
+impl CraneSelection {
+    fn models(&self) -> Vec<CraneModel> {
+        match self {
+            Self::Single(model) => vec![*model],
+            Self::Both => vec![CraneModel::CrateMover9000, CraneModel::CrateMover9001],
+        }
+    }
+}
+
+impl FromStr for CraneSelection {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "9000" => Ok(Self::Single(CraneModel::CrateMover9000)),
+            "9001" => Ok(Self::Single(CraneModel::CrateMover9001)),
+            "both" => Ok(Self::Both),
+            _ => bail!("Unknown crane model '{s}', expected '9000', '9001' or 'both'"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 struct Stack {
     stack: VecDeque<char>,
 }
@@ -24,7 +71,7 @@ impl Stack {
     }
 
     fn push_many(&mut self, lifted_crates: &mut VecDeque<char>) {
-        for crate_id in lifted_crates.into_iter().rev() {
+        for crate_id in lifted_crates.iter_mut().rev() {
             self.stack.push_back(*crate_id);
         }
         // self.stack.append(lifted_crates);
@@ -36,7 +83,6 @@ impl Stack {
 
     fn take_many(&mut self, amount: u32) -> Result<VecDeque<char>, anyhow::Error> {
         (0..amount)
-            .into_iter()
             .map(|_| self.stack.pop_back())
             .collect::<Option<_>>()
             .ok_or(anyhow::Error::msg("Not enough crates on stack!"))
@@ -47,7 +93,7 @@ impl Stack {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct Stacks {
     stacks: Vec<Stack>,
 }
@@ -78,21 +124,41 @@ impl Stacks {
         Ok(())
     }
 
+    // stacks are labelled starting at 1
+    fn stack_index(&self, label: usize) -> Result<usize, anyhow::Error> {
+        label
+            .checked_sub(1)
+            .filter(|idx| *idx < self.stacks.len())
+            .with_context(|| {
+                format!(
+                    "There is no stack {label}, only stacks 1 to {}",
+                    self.stacks.len()
+                )
+            })
+    }
+
+    // checks the whole move up front, so a failing move never leaves the stacks half moved
+    fn check_move(
+        &self,
+        amount: u32,
+        from: usize,
+        to: usize,
+    ) -> Result<(usize, usize), anyhow::Error> {
+        let (from_idx, to_idx) = (self.stack_index(from)?, self.stack_index(to)?);
+        let available = self.stacks[from_idx].stack.len();
+        if (amount as usize) > available {
+            bail!("Cannot move {amount} crates from stack {from}, it only has {available}");
+        }
+        Ok((from_idx, to_idx))
+    }
+
     fn move_crates(&mut self, amount: u32, from: usize, to: usize) -> Result<(), anyhow::Error> {
-        let (from, to) = (from - 1, to - 1);
+        let (from, to) = self.check_move(amount, from, to)?;
         for _idx in 0..amount {
-            let crate_id = self
-                .stacks
-                .get_mut(from)
-                .with_context(|| {
-                    anyhow::Error::msg(format!("There is no stack with index {from}"))
-                })?
+            let crate_id = self.stacks[from]
                 .take()
                 .ok_or_else(|| anyhow::Error::msg(format!("Stack {from} has no more crates!")))?;
-            self.stacks
-                .get_mut(to)
-                .with_context(|| anyhow::Error::msg(format!("There is no stack with index {to}")))?
-                .push(crate_id);
+            self.stacks[to].push(crate_id);
         }
         Ok(())
     }
@@ -103,96 +169,529 @@ impl Stacks {
         from: usize,
         to: usize,
     ) -> Result<(), anyhow::Error> {
-        let (from, to) = (from - 1, to - 1);
-        let mut crates = self
-            .stacks
-            .get_mut(from)
-            .with_context(|| anyhow::Error::msg(format!("There is no stack with index {from}")))?
-            .take_many(amount)?;
-        println!("moving {crates:?}");
-        self.stacks
-            .get_mut(to)
-            .with_context(|| anyhow::Error::msg(format!("There is no stack with index {to}")))?
-            .push_many(&mut crates);
+        let (from, to) = self.check_move(amount, from, to)?;
+        let mut crates = self.stacks[from].take_many(amount)?;
+        self.stacks[to].push_many(&mut crates);
         Ok(())
     }
 
+    fn apply(&mut self, model: CraneModel, step: &Move) -> Result<(), anyhow::Error> {
+        match model {
+            CraneModel::CrateMover9000 => self.move_crates(step.amount, step.from, step.to),
+            CraneModel::CrateMover9001 => self.move_crates9001(step.amount, step.from, step.to),
+        }
+    }
+
     fn get_tops(&self) -> Vec<char> {
         self.stacks.iter().filter_map(Stack::top).collect()
     }
 }
 
-fn parse_stack_drawing(drawing: &Vec<String>) -> Result<Stacks, anyhow::Error> {
-    let number_of_stacks = drawing
-        .last()
-        .ok_or(anyhow::Error::msg("No stack indices"))?
-        .split_whitespace()
-        .filter_map(|s| {
-            let s = s.trim();
-            if s.is_empty() {
-                None
-            } else {
-                Some(s)
+const COLOUR_SOURCE: &str = "\x1b[1;31m";
+const COLOUR_DESTINATION: &str = "\x1b[1;32m";
+const COLOUR_RESET: &str = "\x1b[0m";
+
+impl Stacks {
+    // renders the stacks in the same format as the puzzle drawing,
+    // optionally colouring the source and destination stack of a move
+    fn render(&self, highlight: Option<&Move>) -> String {
+        let colour = |idx: usize, cell: String| -> String {
+            match highlight {
+                Some(step) if step.from == idx + 1 => {
+                    format!("{COLOUR_SOURCE}{cell}{COLOUR_RESET}")
+                }
+                Some(step) if step.to == idx + 1 => {
+                    format!("{COLOUR_DESTINATION}{cell}{COLOUR_RESET}")
+                }
+                _ => cell,
+            }
+        };
+
+        let mut lines: Vec<String> = Vec::new();
+        let height = self.stacks.iter().map(|s| s.stack.len()).max().unwrap_or(0);
+        for level in (0..height).rev() {
+            let mut cells = self
+                .stacks
+                .iter()
+                .map(|s| s.stack.get(level))
+                .collect::<Vec<_>>();
+            // the puzzle drawing has no trailing whitespace
+            while let Some(None) = cells.last() {
+                cells.pop();
             }
+            lines.push(
+                cells
+                    .into_iter()
+                    .enumerate()
+                    .map(|(idx, cell)| match cell {
+                        Some(crate_id) => colour(idx, format!("[{crate_id}]")),
+                        None => "   ".to_string(),
+                    })
+                    .join(" "),
+            );
+        }
+        lines.push(
+            (1..=self.stacks.len())
+                .map(|label| {
+                    let cell = format!("{label:^3}");
+                    if label == self.stacks.len() {
+                        colour(label - 1, cell.trim_end().to_string())
+                    } else {
+                        colour(label - 1, cell)
+                    }
+                })
+                .join(" "),
+        );
+        lines.join("\n")
+    }
+}
+
+impl Display for Stacks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.render(None))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Move {
+    line_number: usize,
+    amount: u32,
+    from: usize,
+    to: usize,
+}
+
+impl Move {
+    // moving the same crates back with the same crane restores the previous order
+    fn reversed(&self) -> Self {
+        Self {
+            line_number: self.line_number,
+            amount: self.amount,
+            from: self.to,
+            to: self.from,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Journal {
+    model: CraneModel,
+    moves: Vec<Move>,
+    // number of moves currently applied to the stacks
+    position: usize,
+}
+
+impl Journal {
+    fn new(model: CraneModel) -> Self {
+        Self {
+            model,
+            moves: Vec::new(),
+            position: 0,
+        }
+    }
+
+    // applies a new move, dropping any moves which were undone before
+    fn apply(&mut self, stacks: &mut Stacks, step: Move) -> Result<(), anyhow::Error> {
+        stacks
+            .apply(self.model, &step)
+            .with_context(|| format!("Line {}: Invalid move", step.line_number))?;
+        self.moves.truncate(self.position);
+        self.moves.push(step);
+        self.position += 1;
+        Ok(())
+    }
+
+    fn undo(&mut self, stacks: &mut Stacks) -> Result<bool, anyhow::Error> {
+        if self.position == 0 {
+            return Ok(false);
+        }
+        stacks.apply(self.model, &self.moves[self.position - 1].reversed())?;
+        self.position -= 1;
+        Ok(true)
+    }
+
+    fn redo(&mut self, stacks: &mut Stacks) -> Result<bool, anyhow::Error> {
+        let Some(step) = self.moves.get(self.position) else {
+            return Ok(false);
+        };
+        stacks.apply(self.model, step)?;
+        self.position += 1;
+        Ok(true)
+    }
+
+    fn current_move(&self) -> Option<&Move> {
+        self.position.checked_sub(1).map(|idx| &self.moves[idx])
+    }
+
+    // undoes or replays moves until exactly `step` moves are applied
+    fn seek(&mut self, stacks: &mut Stacks, step: usize) -> Result<(), anyhow::Error> {
+        if step > self.moves.len() {
+            bail!(
+                "Cannot seek to move {step}, the journal only has {} moves",
+                self.moves.len()
+            );
+        }
+        while self.position > step {
+            self.undo(stacks)?;
+        }
+        while self.position < step {
+            self.redo(stacks)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct Options {
+    file_name: String,
+    crane: CraneSelection,
+    step: Option<usize>,
+    check_drawing: bool,
+    animate: bool,
+    delay: Duration,
+    jump: usize,
+}
+
+impl Options {
+    fn from_args() -> Result<Self, anyhow::Error> {
+        let mut file_name: Option<String> = None;
+        let mut crane = CraneSelection::Both;
+        let mut step: Option<usize> = None;
+        let mut check_drawing = false;
+        let mut animate = false;
+        let mut delay = Duration::from_millis(250);
+        let mut jump: usize = 0;
+
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--crane" => {
+                    crane = args
+                        .next()
+                        .context("--crane requires one of '9000', '9001' or 'both'")?
+                        .parse()?
+                }
+                "--step" => {
+                    step = Some(
+                        args.next()
+                            .context("--step requires a move number")?
+                            .parse()
+                            .context("--step requires a move number")?,
+                    )
+                }
+                "--check-drawing" => check_drawing = true,
+                "--animate" => animate = true,
+                "--delay" => {
+                    delay = Duration::from_millis(
+                        args.next()
+                            .context("--delay requires milliseconds")?
+                            .parse()
+                            .context("--delay requires milliseconds")?,
+                    )
+                }
+                "--jump" => {
+                    jump = args
+                        .next()
+                        .context("--jump requires a move number")?
+                        .parse()
+                        .context("--jump requires a move number")?
+                }
+                flag if flag.starts_with("--") => bail!("Unknown option '{flag}'"),
+                _ if file_name.is_none() => file_name = Some(arg),
+                _ => bail!("Unexpected argument '{arg}'"),
+            }
+        }
+
+        Ok(Self {
+            file_name: file_name.context("No input file supplied!")?,
+            crane,
+            step,
+            check_drawing,
+            animate,
+            delay,
+            jump,
         })
-        .count();
-
-    let mut stacks = Stacks::new(number_of_stacks);
-    for line in drawing.iter().rev().skip(1) {
-        for idx in 0..number_of_stacks {
-            // .with_context(|| {
-            //     anyhow::Error::msg(format!("stack drawing too short (idx={idx}): {line}"))
-            // })?;
-            match line.chars().nth((idx * 4) + 1) {
-                Some(crate_id) if crate_id != ' ' => stacks.push_onto(idx, crate_id)?,
-                _ => {}
+    }
+}
+
+// tabs in the drawing stand in for one column of the drawing, which is 4 characters wide
+fn expand_tabs(line: &str) -> Vec<char> {
+    let mut expanded = Vec::with_capacity(line.len());
+    for c in line.chars() {
+        if c == '\t' {
+            expanded.push(' ');
+            while expanded.len() % 4 != 0 {
+                expanded.push(' ');
+            }
+        } else {
+            expanded.push(c);
+        }
+    }
+    expanded
+}
+
+fn parse_stack_drawing(drawing: &[String]) -> Result<Stacks, anyhow::Error> {
+    let (index_row, crate_rows) = drawing
+        .split_last()
+        .ok_or(anyhow::Error::msg("No stack indices"))?;
+    let index_row_number = drawing.len();
+
+    // every stack label covers a span of columns, which anchors the crates above it
+    let index_row = expand_tabs(index_row);
+    let mut labels: Vec<(usize, usize)> = Vec::new();
+    let mut col = 0;
+    while col < index_row.len() {
+        if index_row[col].is_ascii_digit() {
+            let start = col;
+            while col < index_row.len() && index_row[col].is_ascii_digit() {
+                col += 1;
+            }
+            let label: usize = index_row[start..col].iter().collect::<String>().parse()?;
+            if label != labels.len() + 1 {
+                bail!(
+                    "Line {index_row_number}: Expected stack label {}, found {label}",
+                    labels.len() + 1
+                );
+            }
+            labels.push((start, col));
+        } else if index_row[col].is_whitespace() {
+            col += 1;
+        } else {
+            bail!(
+                "Line {index_row_number}: Unexpected '{}' in stack indices",
+                index_row[col]
+            );
+        }
+    }
+    if labels.is_empty() {
+        bail!("Line {index_row_number}: No stack indices");
+    }
+
+    let mut stacks = Stacks::new(labels.len());
+    for (row_idx, line) in crate_rows.iter().enumerate().rev() {
+        let line_number = row_idx + 1;
+        let line = expand_tabs(line);
+        let mut col = 0;
+        while col < line.len() {
+            match line[col] {
+                c if c.is_whitespace() => col += 1,
+                '[' if line.get(col + 2) == Some(&']') => {
+                    let crate_id = line[col + 1];
+                    let position = col + 1;
+                    // crates have to sit above the span of their label, or at least right next to it
+                    let stack_idx = labels
+                        .iter()
+                        .position(|(start, end)| (*start..*end).contains(&position))
+                        .or_else(|| {
+                            labels.iter().position(|(start, end)| {
+                                position + 1 == *start || position == *end
+                            })
+                        })
+                        .with_context(|| {
+                            format!(
+                                "Line {line_number}: Crate [{crate_id}] in column {} is not above any stack label",
+                                position + 1
+                            )
+                        })?;
+                    stacks.push_onto(stack_idx, crate_id)?;
+                    col += 3;
+                }
+                c => bail!(
+                    "Line {line_number}: Unexpected '{c}' in column {}, expected a crate like [A]",
+                    col + 1
+                ),
             }
         }
     }
 
-    println!("Parsed Stacks: {stacks:?}");
     Ok(stacks)
 }
 
+// renders the parsed stacks again and compares them against the original drawing
+fn check_drawing_round_trip(drawing: &[String], stacks: &Stacks) -> Result<(), anyhow::Error> {
+    let rendered = stacks.to_string();
+    let mismatches = drawing
+        .iter()
+        .map(|line| expand_tabs(line).iter().collect::<String>())
+        .zip_longest(rendered.lines())
+        .enumerate()
+        .filter_map(|(idx, pair)| match pair {
+            EitherOrBoth::Both(expected, actual) if expected.trim_end() == actual => None,
+            EitherOrBoth::Both(expected, actual) => Some(format!(
+                "  line {}: expected '{expected}', rendered '{actual}'",
+                idx + 1
+            )),
+            EitherOrBoth::Left(expected) => Some(format!(
+                "  line {}: expected '{expected}', rendered nothing",
+                idx + 1
+            )),
+            EitherOrBoth::Right(actual) => Some(format!(
+                "  line {}: expected nothing, rendered '{actual}'",
+                idx + 1
+            )),
+        })
+        .collect::<Vec<_>>();
+
+    if !mismatches.is_empty() {
+        bail!(
+            "Stack drawing does not survive a parse/render round trip:\n{}",
+            mismatches.join("\n")
+        );
+    }
+    Ok(())
+}
+
+fn draw_frame(stacks: &Stacks, journal: &Journal) {
+    // clear the terminal and move the cursor to the top left
+    print!("\x1b[2J\x1b[H");
+    match journal.current_move() {
+        Some(step) => println!(
+            "{} - move {}/{} (line {}): move {} from {} to {}",
+            journal.model.name(),
+            journal.position,
+            journal.moves.len(),
+            step.line_number,
+            step.amount,
+            step.from,
+            step.to
+        ),
+        None => println!("{} - move 0/{}", journal.model.name(), journal.moves.len()),
+    }
+    println!();
+    println!("{}", stacks.render(journal.current_move()));
+}
+
+// replays the journal from move `jump` onwards, redrawing the stacks after every move
+fn animate(
+    stacks: &mut Stacks,
+    journal: &mut Journal,
+    jump: usize,
+    delay: Duration,
+) -> Result<(), anyhow::Error> {
+    journal.seek(stacks, jump)?;
+    draw_frame(stacks, journal);
+    while journal.redo(stacks)? {
+        sleep(delay);
+        draw_frame(stacks, journal);
+    }
+    Ok(())
+}
+
 fn main() -> Result<(), Box<dyn Error>> {
-    let file_name = std::env::args().nth(1).expect("No input file supplied!");
-    let lines = BufReader::new(File::open(file_name)?)
-        .lines()
-        .into_iter()
-        .filter_map(|line| line.ok());
+    let options = Options::from_args()?;
+    let lines = BufReader::new(File::open(&options.file_name)?).lines();
 
     let move_regex =
         regex::Regex::new(r#"^move\s+(?P<amount>\d+)\s+from\s+(?P<from>\d+)\s+to\s+(?P<to>\d+)$"#)?;
 
     let mut stack_drawing = Vec::<String>::new();
     let mut stacks: Option<Stacks> = None;
-    for line in lines {
-        if line.is_empty() {
-            stacks = Some(parse_stack_drawing(&stack_drawing)?);
-            println!("{stack_drawing:?}");
-            continue;
+    let mut moves = Vec::<Move>::new();
+    for (idx, line) in lines.enumerate() {
+        let line_number = idx + 1;
+        let line = line?;
+        if line.trim().is_empty() {
+            if stacks.is_none() {
+                stacks = Some(parse_stack_drawing(&stack_drawing)?);
+            }
         } else if stacks.is_none() {
             stack_drawing.push(line);
-            // println!("stack: {line}");
-        } else if let Some(caps) = move_regex.captures(&line) {
-            // println!("move: {line} -> {caps:?}");
-            match (caps.name("amount"), caps.name("from"), caps.name("to")) {
-                (Some(amount), Some(from), Some(to)) => {
-                    //
-                    stacks.as_mut().unwrap().move_crates9001(
-                        amount.as_str().parse()?,
-                        from.as_str().parse()?,
-                        to.as_str().parse()?,
-                    )?
-                }
-                _ => {}
+        } else if let Some(caps) = move_regex.captures(line.trim()) {
+            let parse_group = |name: &str| -> Result<usize, anyhow::Error> {
+                caps[name].parse().with_context(|| {
+                    format!("Line {line_number}: Invalid {name} '{}'", &caps[name])
+                })
+            };
+            let step = Move {
+                line_number,
+                amount: u32::try_from(parse_group("amount")?).with_context(|| {
+                    format!("Line {line_number}: Invalid amount '{}'", &caps["amount"])
+                })?,
+                from: parse_group("from")?,
+                to: parse_group("to")?,
+            };
+            if let Some(stacks) = stacks.as_ref() {
+                stacks
+                    .stack_index(step.from)
+                    .and(stacks.stack_index(step.to))
+                    .with_context(|| {
+                        format!("Line {line_number}: Invalid move '{}'", line.trim())
+                    })?;
             }
+            moves.push(step);
+        } else {
+            return Err(anyhow::Error::msg(format!(
+                "Line {line_number}: Expected 'move <amount> from <stack> to <stack>', found '{line}'"
+            ))
+            .into());
         }
     }
+    let stacks = stacks.context("No empty line after the stack drawing")?;
+    if options.check_drawing {
+        check_drawing_round_trip(&stack_drawing, &stacks)?;
+        println!("Stack drawing survives a parse/render round trip");
+    }
+
+    for model in options.crane.models() {
+        let mut model_stacks = stacks.clone();
+        let mut journal = Journal::new(model);
+        for step in moves.iter() {
+            journal.apply(&mut model_stacks, *step)?;
+        }
+        if options.animate {
+            animate(&mut model_stacks, &mut journal, options.jump, options.delay)?;
+        }
+
+        let tops = model_stacks.get_tops();
+        println!();
+        println!("{}:", model.name());
+        println!("{model_stacks}");
+        println!("tops: {}", tops.iter().map(char::to_string).join(""));
 
-    let stacks = stacks.unwrap();
-    let tops = stacks.get_tops();
-    println!("{stacks:?}");
-    println!("{:?}", tops.iter().map(char::to_string).join(""));
+        if let Some(step) = options.step {
+            journal.seek(&mut model_stacks, step)?;
+            println!();
+            println!("{} after move {step}:", model.name());
+            println!("{model_stacks}");
+        }
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn drawing(input: &str) -> Vec<String> {
+        input
+            .lines()
+            .take_while(|line| !line.trim().is_empty())
+            .map(|line| line.to_string())
+            .collect()
+    }
+
+    fn assert_round_trip(drawing: &[String]) {
+        let stacks = parse_stack_drawing(drawing).expect("drawing should parse");
+        let expected = drawing
+            .iter()
+            .map(|line| line.trim_end())
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert_eq!(stacks.to_string().trim_end_matches('\n'), expected);
+        check_drawing_round_trip(drawing, &stacks).expect("drawing should round trip");
+    }
+
+    #[test]
+    fn mock_drawing_round_trip() {
+        assert_round_trip(&drawing(include_str!("../mock.txt")));
+    }
+
+    #[test]
+    fn drawing_with_more_than_nine_stacks_round_trip() {
+        assert_round_trip(&drawing(concat!(
+            "                                        [K]\n",
+            "[A]                 [F]             [J] [L]\n",
+            "[B] [C] [D] [E] [G] [H]         [I] [M] [N]\n",
+            " 1   2   3   4   5   6   7   8   9  10  11 \n",
+        )));
+    }
+}
@@ -1,38 +1,443 @@
-use itertools::Itertools;
+use anyhow::{bail, Context};
 use std::{
-    collections::HashSet,
+    collections::VecDeque,
     error::Error,
     fs::File,
-    io::{BufRead, BufReader},
+    io::{BufRead, BufReader, Read},
 };
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let file_name = std::env::args().nth(1).expect("No input file supplied!");
-    let lines = BufReader::new(File::open(file_name)?)
-        .lines()
-        .into_iter()
-        .filter_map(|line| line.ok());
-
-    for line in lines {
-        print!("{line}");
-        let pos = line
-            .chars()
-            .tuple_windows::<(_, _, _, _)>()
-            .find_position(|(a, b, c, d)| a != b && a != c && a != d && b != c && b != d && c != d);
+// tracks whether the last `window` bytes of a stream are all different in O(1) per byte
+#[derive(Debug)]
+struct MarkerDetector {
+    window: usize,
+    buffer: VecDeque<u8>,
+    counts: [u32; 256],
+    // number of byte values occurring more than once in the buffer
+    duplicates: usize,
+}
+
+impl MarkerDetector {
+    fn new(window: usize) -> Self {
+        Self {
+            window,
+            buffer: VecDeque::new(),
+            counts: [0; 256],
+            duplicates: 0,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.buffer.clear();
+        self.counts = [0; 256];
+        self.duplicates = 0;
+    }
+
+    // feeds the next byte and returns whether the window ending with it is a marker
+    fn push(&mut self, byte: u8) -> bool {
+        self.buffer.push_back(byte);
+        self.counts[byte as usize] += 1;
+        if self.counts[byte as usize] == 2 {
+            self.duplicates += 1;
+        }
+
+        if self.buffer.len() > self.window {
+            if let Some(old) = self.buffer.pop_front() {
+                self.counts[old as usize] -= 1;
+                if self.counts[old as usize] == 1 {
+                    self.duplicates -= 1;
+                }
+            }
+        }
+
+        self.buffer.len() == self.window && self.duplicates == 0
+    }
+
+    fn name(&self) -> String {
+        match self.window {
+            4 => "start of packet".to_string(),
+            14 => "start of message".to_string(),
+            window => format!("window of {window}"),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Options {
+    file_name: String,
+    windows: Vec<usize>,
+    all: bool,
+    decode: bool,
+}
+
+impl Options {
+    fn from_args() -> Result<Self, anyhow::Error> {
+        let mut file_name: Option<String> = None;
+        let mut windows: Vec<usize> = Vec::new();
+        let mut all = false;
+        let mut decode = false;
+
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--window" => {
+                    let window: usize = args
+                        .next()
+                        .context("--window requires a size")?
+                        .parse()
+                        .context("--window requires a size")?;
+                    if window == 0 {
+                        bail!("--window has to be at least 1");
+                    }
+                    windows.push(window);
+                }
+                "--all" => all = true,
+                "--decode" => decode = true,
+                flag if flag.starts_with("--") => bail!("Unknown option '{flag}'"),
+                _ if file_name.is_none() => file_name = Some(arg),
+                _ => bail!("Unexpected argument '{arg}'"),
+            }
+        }
+        if windows.is_empty() {
+            windows = vec![4, 14];
+        }
+        if decode && windows.len() != 2 {
+            bail!("--decode needs exactly two windows, one for packets and one for messages");
+        }
+
+        Ok(Self {
+            file_name: file_name.context("No input file supplied!")?,
+            windows,
+            all,
+            decode,
+        })
+    }
+}
+
+fn print_markers(stream: usize, detector: &MarkerDetector, markers: &[usize], all: bool) {
+    match (markers.first(), all) {
+        (None, _) => println!("stream {stream}: {} => none", detector.name()),
+        (Some(first), false) => println!("stream {stream}: {} => {first}", detector.name()),
+        (Some(_), true) => println!(
+            "stream {stream}: {} => {} markers {markers:?}",
+            detector.name(),
+            markers.len()
+        ),
+    }
+}
+
+// Packets run from their start-of-packet marker up to the marker of the next packet, or the
+// end of the stream. Their message is the first start-of-message marker completing inside
+// the packet and runs up to the end of the packet, so a message is always part of a packet
+// and ends where the next start-of-packet marker begins. After a message, the next packet
+// only needs a window with a repeated byte followed by a start-of-packet marker, so on
+// random payload a message can be just a few bytes long.
+#[derive(Debug)]
+struct Segment {
+    stream: usize,
+    kind: &'static str,
+    index: usize,
+    // offset of the first marker byte
+    marker: usize,
+    // offset of the first payload byte after the marker
+    start: usize,
+    length: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DecodeState {
+    // no packet started yet
+    Searching,
+    // inside a packet, waiting for its message
+    InPacket,
+    // Inside the message of a packet. A new packet needs a window with a repeated byte
+    // first, so the payload of the message doesn't immediately count as a new marker.
+    InMessage { armed: bool },
+}
+
+struct Decoder {
+    packet: MarkerDetector,
+    message: MarkerDetector,
+    state: DecodeState,
+    // (marker, start) of the current packet and its message
+    current_packet: Option<(usize, usize)>,
+    current_message: Option<(usize, usize)>,
+    packets: usize,
+}
+
+impl Decoder {
+    fn new(packet_window: usize, message_window: usize) -> Self {
+        Self {
+            packet: MarkerDetector::new(packet_window),
+            message: MarkerDetector::new(message_window),
+            state: DecodeState::Searching,
+            current_packet: None,
+            current_message: None,
+            packets: 0,
+        }
+    }
+
+    // `position` is the number of bytes processed including this one,
+    // returns the segments of a packet that just ended
+    fn push(&mut self, stream: usize, position: usize, byte: u8) -> Vec<Segment> {
+        let packet_marker = self.packet.push(byte);
+        let message_marker = self.message.push(byte);
+        let packet_start = position.saturating_sub(self.packet.window);
+
+        match self.state {
+            DecodeState::Searching if packet_marker => {
+                self.current_packet = Some((packet_start, position));
+                self.state = DecodeState::InPacket;
+            }
+            DecodeState::InPacket if message_marker => {
+                self.current_message = Some((position - self.message.window, position));
+                self.state = DecodeState::InMessage { armed: false };
+            }
+            DecodeState::InMessage { armed: false } if !packet_marker => {
+                self.state = DecodeState::InMessage { armed: true };
+            }
+            DecodeState::InMessage { armed: true }
+                if packet_marker
+                    && self
+                        .current_message
+                        .is_some_and(|(_, message_start)| packet_start >= message_start) =>
+            {
+                let segments = self.finish(stream, packet_start);
+                self.current_packet = Some((packet_start, position));
+                self.state = DecodeState::InPacket;
+                return segments;
+            }
+            _ => {}
+        }
+        Vec::new()
+    }
+
+    // closes the current packet at `end` and starts over for the next stream
+    fn finish(&mut self, stream: usize, end: usize) -> Vec<Segment> {
+        let mut segments = Vec::new();
+        if let Some((marker, start)) = self.current_packet.take() {
+            self.packets += 1;
+            segments.push(Segment {
+                stream,
+                kind: "packet",
+                index: self.packets,
+                marker,
+                start,
+                length: end - start,
+            });
+            if let Some((marker, start)) = self.current_message.take() {
+                segments.push(Segment {
+                    stream,
+                    kind: "message",
+                    index: self.packets,
+                    marker,
+                    start,
+                    length: end - start,
+                });
+            }
+        }
+        self.state = DecodeState::Searching;
+        segments
+    }
+
+    fn reset(&mut self) {
+        self.packet.reset();
+        self.message.reset();
+        self.state = DecodeState::Searching;
+        self.current_packet = None;
+        self.current_message = None;
+        self.packets = 0;
+    }
+}
+
+fn print_segment(segment: &Segment) {
+    println!(
+        "{:>6} {:<8} {:>6} {:>8} {:>8} {:>8}",
+        segment.stream, segment.kind, segment.index, segment.marker, segment.start, segment.length
+    );
+}
+
+fn print_summary(segments: &[Segment]) {
+    println!();
+    println!(
+        "{:<8} {:>8} {:>10} {:>8} {:>8} {:>10}",
+        "kind", "segments", "total", "min", "max", "mean"
+    );
+    for kind in ["packet", "message"] {
+        let lengths = segments
+            .iter()
+            .filter(|segment| segment.kind == kind)
+            .map(|segment| segment.length)
+            .collect::<Vec<_>>();
+        let total: usize = lengths.iter().sum();
+        let mean = if lengths.is_empty() {
+            0.0
+        } else {
+            total as f64 / lengths.len() as f64
+        };
         println!(
-            "start of packet => {:?}",
-            pos.map(|(p, chars)| (p + 4, chars))
+            "{:<8} {:>8} {:>10} {:>8} {:>8} {:>10.2}",
+            kind,
+            lengths.len(),
+            total,
+            lengths.iter().min().unwrap_or(&0),
+            lengths.iter().max().unwrap_or(&0),
+            mean
         );
+    }
+}
+
+fn decode(
+    reader: impl BufRead,
+    packet_window: usize,
+    message_window: usize,
+) -> Result<(), Box<dyn Error>> {
+    let mut decoder = Decoder::new(packet_window, message_window);
+    let mut segments: Vec<Segment> = Vec::new();
+    let mut stream: usize = 1;
+    let mut position: usize = 0;
 
-        for pos in 14..line.len() {
-            let window = &line[(pos - 14)..pos];
-            let unique_window: HashSet<char> = HashSet::from_iter(window.chars());
-            if unique_window.len() == 14 {
-                println!("start of message => {pos} {window}");
-                break;
+    println!(
+        "{:>6} {:<8} {:>6} {:>8} {:>8} {:>8}",
+        "stream", "kind", "packet", "marker", "start", "length"
+    );
+    // every line of the input is a separate datastream
+    for byte in reader.bytes() {
+        let byte = byte?;
+        if byte == b'\n' || byte == b'\r' {
+            if position > 0 {
+                segments.extend(decoder.finish(stream, position));
+                decoder.reset();
+                stream += 1;
+                position = 0;
             }
+            continue;
         }
+
+        position += 1;
+        segments.extend(decoder.push(stream, position, byte));
+    }
+    if position > 0 {
+        segments.extend(decoder.finish(stream, position));
     }
 
+    for segment in segments.iter() {
+        print_segment(segment);
+    }
+    print_summary(&segments);
     Ok(())
 }
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let options = Options::from_args()?;
+    let reader = BufReader::new(File::open(&options.file_name)?);
+
+    if options.decode {
+        return decode(reader, options.windows[0], options.windows[1]);
+    }
+
+    let mut detectors = options
+        .windows
+        .iter()
+        .map(|window| MarkerDetector::new(*window))
+        .collect::<Vec<_>>();
+    // positions are the number of bytes processed when the marker is complete
+    let mut markers: Vec<Vec<usize>> = vec![Vec::new(); detectors.len()];
+    let mut stream: usize = 1;
+    let mut position: usize = 0;
+
+    let finish_stream =
+        |stream: usize, detectors: &mut Vec<MarkerDetector>, markers: &mut Vec<Vec<usize>>| {
+            for (detector, markers) in detectors.iter_mut().zip(markers.iter_mut()) {
+                print_markers(stream, detector, markers, options.all);
+                detector.reset();
+                markers.clear();
+            }
+        };
+
+    // every line of the input is a separate datastream
+    for byte in reader.bytes() {
+        let byte = byte?;
+        if byte == b'\n' || byte == b'\r' {
+            if position > 0 {
+                finish_stream(stream, &mut detectors, &mut markers);
+                stream += 1;
+                position = 0;
+            }
+            continue;
+        }
+
+        position += 1;
+        for (detector, markers) in detectors.iter_mut().zip(markers.iter_mut()) {
+            if detector.push(byte) && (options.all || markers.is_empty()) {
+                markers.push(position);
+            }
+        }
+    }
+    if position > 0 {
+        finish_stream(stream, &mut detectors, &mut markers);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // (kind, packet, marker, start, length) of every segment of a single stream
+    fn segments(
+        stream: &str,
+        packet_window: usize,
+        message_window: usize,
+    ) -> Vec<(&'static str, usize, usize, usize, usize)> {
+        let mut decoder = Decoder::new(packet_window, message_window);
+        let mut segments = Vec::new();
+        for (idx, byte) in stream.bytes().enumerate() {
+            segments.extend(decoder.push(1, idx + 1, byte));
+        }
+        segments.extend(decoder.finish(1, stream.len()));
+        segments
+            .into_iter()
+            .map(|segment| {
+                (
+                    segment.kind,
+                    segment.index,
+                    segment.marker,
+                    segment.start,
+                    segment.length,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn no_segments_without_a_packet_marker() {
+        assert_eq!(segments("aaaaaaaa", 4, 6), vec![]);
+    }
+
+    #[test]
+    fn packet_without_message_runs_to_the_end() {
+        assert_eq!(segments("aabcdaabb", 4, 6), vec![("packet", 1, 1, 5, 4)]);
+    }
+
+    #[test]
+    fn message_runs_to_the_end_of_its_packet() {
+        // "xyzq" right after the message is a packet marker, but has no repeated byte before it
+        assert_eq!(
+            segments("aaaabcdxxuvwxyzqq", 4, 6),
+            vec![("packet", 1, 3, 7, 10), ("message", 1, 9, 15, 2)]
+        );
+    }
+
+    #[test]
+    fn message_ends_at_the_next_packet_marker() {
+        assert_eq!(
+            segments("aaaabcdxxuvwxyzqqpqrsttt", 4, 6),
+            vec![
+                ("packet", 1, 3, 7, 10),
+                ("message", 1, 9, 15, 2),
+                ("packet", 2, 17, 21, 3),
+            ]
+        );
+    }
+}
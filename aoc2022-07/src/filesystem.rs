@@ -0,0 +1,258 @@
+use std::{collections::BTreeMap, fmt::Write};
+
+// sizes like `du -h`, using powers of 1024
+pub fn human_size(size: u64) -> String {
+    const UNITS: [&str; 6] = ["B", "K", "M", "G", "T", "P"];
+    let mut value = size as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{size}{}", UNITS[0])
+    } else {
+        format!("{value:.1}{}", UNITS[unit])
+    }
+}
+
+fn json_string(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len() + 2);
+    escaped.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(escaped, "\\u{:04x}", c as u32);
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+#[derive(Debug)]
+pub enum NodeKind {
+    Directory { children: BTreeMap<String, usize> },
+    File,
+}
+
+#[derive(Debug)]
+pub struct Node {
+    pub name: String,
+    pub parent: Option<usize>,
+    pub kind: NodeKind,
+    // for directories this is the recursive size, once computed
+    pub size: u64,
+}
+
+impl Node {
+    pub fn is_directory(&self) -> bool {
+        matches!(self.kind, NodeKind::Directory { .. })
+    }
+}
+
+// arena of all nodes, children are always stored after their parent
+#[derive(Debug)]
+pub struct FileSystem {
+    nodes: Vec<Node>,
+}
+
+impl FileSystem {
+    pub const ROOT: usize = 0;
+
+    pub fn new() -> Self {
+        Self {
+            nodes: vec![Node {
+                name: "/".to_string(),
+                parent: None,
+                kind: NodeKind::Directory {
+                    children: BTreeMap::new(),
+                },
+                size: 0,
+            }],
+        }
+    }
+
+    pub fn node(&self, idx: usize) -> &Node {
+        &self.nodes[idx]
+    }
+
+    pub fn child(&self, dir: usize, name: &str) -> Option<usize> {
+        match &self.nodes[dir].kind {
+            NodeKind::Directory { children } => children.get(name).copied(),
+            NodeKind::File => None,
+        }
+    }
+
+    pub fn children(&self, dir: usize) -> impl Iterator<Item = usize> + '_ {
+        let children = match &self.nodes[dir].kind {
+            NodeKind::Directory { children } => Some(children.values().copied()),
+            NodeKind::File => None,
+        };
+        children.into_iter().flatten()
+    }
+
+    fn add_node(&mut self, parent: usize, name: &str, kind: NodeKind, size: u64) -> usize {
+        let idx = self.nodes.len();
+        self.nodes.push(Node {
+            name: name.to_string(),
+            parent: Some(parent),
+            kind,
+            size,
+        });
+        if let NodeKind::Directory { children } = &mut self.nodes[parent].kind {
+            children.insert(name.to_string(), idx);
+        }
+        idx
+    }
+
+    // returns the existing directory, if it was already added before
+    pub fn add_directory(&mut self, parent: usize, name: &str) -> usize {
+        if let Some(idx) = self.child(parent, name) {
+            return idx;
+        }
+        self.add_node(
+            parent,
+            name,
+            NodeKind::Directory {
+                children: BTreeMap::new(),
+            },
+            0,
+        )
+    }
+
+    pub fn add_file(&mut self, parent: usize, name: &str, size: u64) -> usize {
+        if let Some(idx) = self.child(parent, name) {
+            self.nodes[idx].size = size;
+            return idx;
+        }
+        self.add_node(parent, name, NodeKind::File, size)
+    }
+
+    // sums up the directory sizes in a single pass, as children always come after their parent
+    pub fn compute_sizes(&mut self) {
+        for node in self.nodes.iter_mut() {
+            if node.is_directory() {
+                node.size = 0;
+            }
+        }
+        for idx in (1..self.nodes.len()).rev() {
+            if let Some(parent) = self.nodes[idx].parent {
+                self.nodes[parent].size += self.nodes[idx].size;
+            }
+        }
+    }
+
+    pub fn size(&self, idx: usize) -> u64 {
+        self.nodes[idx].size
+    }
+
+    pub fn directories(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.nodes.len()).filter(|idx| self.nodes[*idx].is_directory())
+    }
+
+    // resolves an absolute path like `/a/e`
+    pub fn lookup(&self, path: &str) -> Option<usize> {
+        path.split('/')
+            .filter(|name| !name.is_empty())
+            .try_fold(Self::ROOT, |dir, name| self.child(dir, name))
+    }
+
+    pub fn path(&self, idx: usize) -> String {
+        let mut names = Vec::new();
+        let mut current = Some(idx);
+        while let Some(node) = current.filter(|node| *node != Self::ROOT) {
+            names.push(self.nodes[node].name.as_str());
+            current = self.nodes[node].parent;
+        }
+        names.reverse();
+        format!("/{}", names.join("/"))
+    }
+
+    // directories up to `max_depth` levels below the root, the root itself has depth 0
+    pub fn directories_up_to(&self, max_depth: usize) -> Vec<usize> {
+        let mut found = Vec::new();
+        let mut queue = vec![(Self::ROOT, 0)];
+        while let Some((dir, depth)) = queue.pop() {
+            found.push(dir);
+            if depth < max_depth {
+                queue.extend(
+                    self.children(dir)
+                        .filter(|child| self.nodes[*child].is_directory())
+                        .map(|child| (child, depth + 1)),
+                );
+            }
+        }
+        found
+    }
+
+    pub fn render_tree(&self) -> String {
+        let mut output = String::new();
+        let root = &self.nodes[Self::ROOT];
+        let _ = writeln!(output, "{} ({})", root.name, human_size(root.size));
+        self.render_children(Self::ROOT, "", &mut output);
+        output
+    }
+
+    fn render_children(&self, dir: usize, prefix: &str, output: &mut String) {
+        let children = self.children(dir).collect::<Vec<_>>();
+        for (idx, child) in children.iter().enumerate() {
+            let last = idx == children.len() - 1;
+            let node = &self.nodes[*child];
+            let _ = writeln!(
+                output,
+                "{prefix}{}{}{} ({})",
+                if last { "└── " } else { "├── " },
+                node.name,
+                if node.is_directory() { "/" } else { "" },
+                human_size(node.size)
+            );
+            if node.is_directory() {
+                let prefix = format!("{prefix}{}", if last { "    " } else { "│   " });
+                self.render_children(*child, &prefix, output);
+            }
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        let mut output = String::new();
+        self.write_json(Self::ROOT, 0, &mut output);
+        output.push('\n');
+        output
+    }
+
+    fn write_json(&self, idx: usize, indent: usize, output: &mut String) {
+        let node = &self.nodes[idx];
+        let pad = "  ".repeat(indent + 1);
+        let _ = write!(
+            output,
+            "{{\n{pad}\"name\": {},\n{pad}\"type\": \"{}\",\n{pad}\"size\": {}",
+            json_string(&node.name),
+            if node.is_directory() {
+                "directory"
+            } else {
+                "file"
+            },
+            node.size
+        );
+        if node.is_directory() {
+            let _ = write!(output, ",\n{pad}\"children\": [");
+            for (child_idx, child) in self.children(idx).enumerate() {
+                output.push_str(if child_idx == 0 { "\n" } else { ",\n" });
+                output.push_str(&"  ".repeat(indent + 2));
+                self.write_json(child, indent + 2, output);
+            }
+            if self.children(idx).next().is_some() {
+                let _ = write!(output, "\n{pad}");
+            }
+            output.push(']');
+        }
+        let _ = write!(output, "\n{}}}", "  ".repeat(indent));
+    }
+}
@@ -1,122 +1,185 @@
 use std::{
-    collections::{HashMap, HashSet},
     error::Error,
-    fs::File,
+    fs::{write, File},
     io::{BufRead, BufReader},
 };
 
-use anyhow::Context;
-use regex::Regex;
+use anyhow::{bail, Context};
+use filesystem::{human_size, FileSystem};
+use parser::{build_filesystem, parse_transcript};
+use planner::{plan_multiple, plan_single, Planner};
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let file_name = std::env::args().nth(1).expect("No input file supplied!");
-    let lines = BufReader::new(File::open(file_name)?)
-        .lines()
-        .into_iter()
-        .filter_map(|line| line.ok());
+mod filesystem;
+mod parser;
+mod planner;
 
-    let rset = vec![
-        // cd command
-        r"^\$\s+cd\s+(?P<name>[a-z0-9\./]+)$",
-        // ls command
-        r"^\$\s+ls$",
-        // dir response
-        r"^dir\s+(?P<name>[a-z0-9\.]+)$",
-        // file response
-        r"^(?P<size>\d+)\s(?P<name>[a-z0-9\.]+)$",
-    ]
-    .iter()
-    .map(|r| Regex::new(r).with_context(|| format!("Failed to compile regex '{r}'")))
-    .collect::<Result<Vec<_>, _>>()?;
+#[derive(Debug)]
+struct Options {
+    file_name: String,
+    // paths to print the size of
+    sizes: Vec<String>,
+    tree: bool,
+    max_depth: Option<usize>,
+    // `-` writes the JSON to stdout
+    json: Option<String>,
+    disk_size: u64,
+    update_size: u64,
+    planner: Planner,
+}
 
-    let mut file_sizes: HashMap<Vec<String>, u64> = HashMap::new();
-    let mut directories: HashSet<Vec<String>> = HashSet::new();
-    let mut current_dir: Vec<String> = Vec::new();
-    for line in lines {
-        let Some((idx, caps)) = rset
-            .iter()
-            .enumerate()
-            .filter_map(|(idx, r)| r.captures(&line).map(|caps| (idx, caps)))
-            .take(1)
-            .next() else { continue; };
+impl Options {
+    fn from_args() -> Result<Self, anyhow::Error> {
+        let mut file_name: Option<String> = None;
+        let mut sizes: Vec<String> = Vec::new();
+        let mut tree = false;
+        let mut max_depth: Option<usize> = None;
+        let mut json: Option<String> = None;
+        let mut disk_size: u64 = 70000000;
+        let mut update_size: u64 = 30000000;
+        let mut planner = Planner::Single;
 
-        match (idx, caps.name("name").map(|g| g.as_str())) {
-            // cd command
-            (0, Some("/")) => {
-                current_dir.clear();
-            }
-            (0, Some("..")) => {
-                current_dir.pop();
-            }
-            (0, Some(name)) => current_dir.extend(name.split("/").map(|s| s.to_string())),
-            // ls command
-            (1, _) => {}
-            // dir response
-            (2, _) => {
-                directories.insert(current_dir.clone());
-            }
-            // file response
-            (3, Some(name)) => {
-                directories.insert(current_dir.clone());
-
-                let size = caps
-                    .name("size")
-                    .map(|s| s.as_str().parse::<u64>().unwrap())
-                    .ok_or_else(|| anyhow::Error::msg("Failed to parse size!"))?;
-                let mut path = current_dir.clone();
-                path.push(name.to_string());
-                file_sizes.insert(path, size);
-            }
-            _ => {
-                panic!("Unknown line input! {idx} {caps:?} '{line}'")
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--size" => sizes.push(args.next().context("--size requires a path")?),
+                "--tree" => tree = true,
+                "--du" => max_depth = Some(max_depth.unwrap_or(usize::MAX)),
+                "--max-depth" => {
+                    max_depth = Some(
+                        args.next()
+                            .context("--max-depth requires a number")?
+                            .parse()
+                            .context("--max-depth requires a number")?,
+                    )
+                }
+                "--json" => json = Some(args.next().context("--json requires a file or '-'")?),
+                "--disk-size" => {
+                    disk_size = args
+                        .next()
+                        .context("--disk-size requires a number of bytes")?
+                        .parse()
+                        .context("--disk-size requires a number of bytes")?
+                }
+                "--update-size" => {
+                    update_size = args
+                        .next()
+                        .context("--update-size requires a number of bytes")?
+                        .parse()
+                        .context("--update-size requires a number of bytes")?
+                }
+                "--planner" => {
+                    planner = args
+                        .next()
+                        .context("--planner requires single or multiple")?
+                        .parse()?
+                }
+                flag if flag.starts_with("--") => bail!("Unknown option '{flag}'"),
+                _ if file_name.is_none() => file_name = Some(arg),
+                _ => bail!("Unexpected argument '{arg}'"),
             }
         }
+
+        Ok(Self {
+            file_name: file_name.context("No input file supplied!")?,
+            sizes,
+            tree,
+            max_depth,
+            json,
+            disk_size,
+            update_size,
+            planner,
+        })
     }
-    println!("{directories:?}");
-    println!("{file_sizes:?}");
-    println!();
+}
 
-    let mut directory_sizes: HashMap<Vec<String>, u64> = HashMap::new();
-    for path in directories.iter() {
-        let dir_size: u64 = file_sizes
-            .iter()
-            .filter_map(|(file_path, size)| {
-                if !path.iter().zip(file_path).all(|(a, b)| a == b) {
-                    // path of file does not match
-                    return None;
-                }
-                Some(size)
-            })
-            .sum();
-        println!("{dir_size} {path:?}");
-        directory_sizes.insert(path.clone(), dir_size);
+fn main() -> Result<(), Box<dyn Error>> {
+    let options = Options::from_args()?;
+    let lines = BufReader::new(File::open(&options.file_name)?).lines();
+
+    let commands = parse_transcript(lines)?;
+    let (mut filesystem, warnings) = build_filesystem(&commands)?;
+    for warning in warnings {
+        eprintln!("Warning: {warning}");
+    }
+    filesystem.compute_sizes();
+
+    for path in options.sizes.iter() {
+        let node = filesystem
+            .lookup(path)
+            .with_context(|| format!("There is no '{path}' in the filesystem"))?;
+        println!("{} {path}", filesystem.size(node));
     }
-    let small_dir_total_size: u64 = directory_sizes
-        .iter()
-        .filter_map(|(_, size)| if *size < 100000 { Some(size) } else { None })
+
+    if options.tree {
+        print!("{}", filesystem.render_tree());
+        println!();
+    }
+    if let Some(max_depth) = options.max_depth {
+        let mut dirs = filesystem.directories_up_to(max_depth);
+        dirs.sort_by_key(|dir| std::cmp::Reverse(filesystem.size(*dir)));
+        for dir in dirs {
+            println!(
+                "{:>8}  {}",
+                human_size(filesystem.size(dir)),
+                filesystem.path(dir)
+            );
+        }
+        println!();
+    }
+    match options.json.as_deref() {
+        Some("-") => print!("{}", filesystem.to_json()),
+        Some(json_file) => write(json_file, filesystem.to_json())
+            .with_context(|| format!("Failed to write JSON to '{json_file}'"))?,
+        None => {}
+    }
+    let small_dir_total_size: u64 = filesystem
+        .directories()
+        .map(|dir| filesystem.size(dir))
+        .filter(|size| *size < 100000)
         .sum();
     println!("=> Total size of directories <100000 = {small_dir_total_size}");
 
-    static TOTAL_SPACE: u64 = 70000000;
-    static REQUIRED_UPDATE_SPACE: u64 = 30000000;
-    let total_size = file_sizes.iter().map(|(_, size)| *size).sum::<u64>();
-    let current_free_space = TOTAL_SPACE - total_size;
+    let total_size = filesystem.size(FileSystem::ROOT);
+    let current_free_space = options.disk_size.checked_sub(total_size).with_context(|| {
+        format!(
+            "The files take up {total_size} bytes, more than the disk size of {}",
+            options.disk_size
+        )
+    })?;
     // amount of space we need to free
-    let required_space = REQUIRED_UPDATE_SPACE - current_free_space;
-    let mut delete_candidates = directory_sizes
-        .iter()
-        .filter(|(_, size)| **size > required_space)
-        .collect::<Vec<_>>();
+    let Some(required_space) = options
+        .update_size
+        .checked_sub(current_free_space)
+        .filter(|space| *space > 0)
+    else {
+        println!("=> {current_free_space} bytes are free already, no need to delete anything");
+        return Ok(());
+    };
 
-    delete_candidates.sort_by(|(_, a), (_, b)| a.cmp(b));
-    println!("Candidates to delete to make room for the update:");
-    println!(
-        "{}",
-        delete_candidates
-            .iter()
-            .map(|(path, size)| format!("{size} => /{}\n", path.join("/")))
-            .collect::<String>()
-    );
+    match options.planner {
+        Planner::Single => {
+            let dir = plan_single(&filesystem, required_space)
+                .context("Even deleting everything won't free enough space")?;
+            println!(
+                "=> Smallest directory to delete = {} => {}",
+                filesystem.size(dir),
+                filesystem.path(dir)
+            );
+        }
+        Planner::Multiple => {
+            let mut dirs = plan_multiple(&filesystem, required_space)?
+                .context("Even deleting everything won't free enough space")?;
+            dirs.sort_by_cached_key(|dir| filesystem.path(*dir));
+            let freed: u64 = dirs.iter().map(|dir| filesystem.size(*dir)).sum();
+            println!(
+                "=> Smallest set of directories to delete = {freed} ({} directories)",
+                dirs.len()
+            );
+            for dir in dirs {
+                println!("{} => {}", filesystem.size(dir), filesystem.path(dir));
+            }
+        }
+    }
 
     Ok(())
 }
@@ -0,0 +1,234 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    io,
+};
+
+use anyhow::{bail, Context, Result};
+
+use crate::filesystem::FileSystem;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Entry {
+    Directory(String),
+    File { name: String, size: u64 },
+}
+
+impl Entry {
+    pub fn name(&self) -> &str {
+        match self {
+            Entry::Directory(name) => name,
+            Entry::File { name, .. } => name,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    // the path as written, absolute if it starts with `/`
+    Cd(String),
+    Ls(Vec<Entry>),
+}
+
+#[derive(Debug, Clone)]
+pub struct TranscriptCommand {
+    pub line_number: usize,
+    pub command: Command,
+}
+
+fn check_name(name: &str) -> Result<&str> {
+    if name.is_empty() {
+        bail!("Missing name");
+    }
+    if name == "." || name == ".." || name.contains('/') {
+        bail!("'{name}' is not a valid file or directory name");
+    }
+    Ok(name)
+}
+
+fn parse_entry(line: &str) -> Result<Entry> {
+    let (head, name) = line
+        .split_once(char::is_whitespace)
+        .with_context(|| format!("Expected 'dir <name>' or '<size> <name>', got '{line}'"))?;
+    let name = check_name(name.trim_start())?.to_string();
+    if head == "dir" {
+        return Ok(Entry::Directory(name));
+    }
+    let size = head
+        .parse::<u64>()
+        .with_context(|| format!("'{head}' is not a valid file size"))?;
+    Ok(Entry::File { name, size })
+}
+
+// `$ cd <path>` and `$ ls`, each followed by the output lines of that command
+pub fn parse_transcript(
+    lines: impl Iterator<Item = io::Result<String>>,
+) -> Result<Vec<TranscriptCommand>> {
+    let mut commands: Vec<TranscriptCommand> = Vec::new();
+    for (idx, line) in lines.enumerate() {
+        let line_number = idx + 1;
+        let line = line.with_context(|| format!("Line {line_number}: Failed to read line"))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let parsed = if let Some(command) = line.strip_prefix('$') {
+            let command = command.trim_start();
+            let (name, argument) = command
+                .split_once(char::is_whitespace)
+                .map(|(name, argument)| (name, argument.trim_start()))
+                .unwrap_or((command, ""));
+            match name {
+                "cd" if argument.is_empty() => Err(anyhow::Error::msg("'cd' requires a path")),
+                "cd" => Ok(Some(Command::Cd(argument.to_string()))),
+                "ls" if argument.is_empty() => Ok(Some(Command::Ls(Vec::new()))),
+                "ls" => Err(anyhow::Error::msg("'ls' takes no arguments")),
+                _ => Err(anyhow::Error::msg(format!("Unknown command '{command}'"))),
+            }
+        } else {
+            match commands.last_mut() {
+                Some(TranscriptCommand {
+                    command: Command::Ls(entries),
+                    ..
+                }) => parse_entry(&line).map(|entry| {
+                    entries.push(entry);
+                    None
+                }),
+                _ => Err(anyhow::Error::msg(format!(
+                    "Output '{line}' does not belong to an 'ls' command"
+                ))),
+            }
+        };
+
+        match parsed {
+            Ok(Some(command)) => commands.push(TranscriptCommand {
+                line_number,
+                command,
+            }),
+            Ok(None) => {}
+            Err(err) => bail!("Line {line_number}: {err:#}"),
+        }
+    }
+    Ok(commands)
+}
+
+// returns the new directory and whether it had to be created, because it was never listed
+fn change_directory(
+    filesystem: &mut FileSystem,
+    current_dir: usize,
+    path: &str,
+) -> Result<(usize, bool)> {
+    let mut created = false;
+    let mut dir = if path.starts_with('/') {
+        FileSystem::ROOT
+    } else {
+        current_dir
+    };
+    for name in path.split('/').filter(|name| !name.is_empty()) {
+        dir = match name {
+            "." => dir,
+            ".." => filesystem
+                .node(dir)
+                .parent
+                .with_context(|| format!("Can't go above the root directory in '{path}'"))?,
+            name => match filesystem.child(dir, name) {
+                Some(child) if !filesystem.node(child).is_directory() => {
+                    bail!("'{}' is a file, not a directory", filesystem.path(child))
+                }
+                Some(child) => child,
+                None => {
+                    created = true;
+                    filesystem.add_directory(dir, name)
+                }
+            },
+        };
+    }
+    Ok((dir, created))
+}
+
+// replays the transcript onto a fresh filesystem, returning it with all warnings
+pub fn build_filesystem(commands: &[TranscriptCommand]) -> Result<(FileSystem, Vec<String>)> {
+    let mut filesystem = FileSystem::new();
+    let mut warnings: Vec<String> = Vec::new();
+    // line number and content of the first listing of each directory
+    let mut listings: HashMap<usize, (usize, BTreeMap<&str, &Entry>)> = HashMap::new();
+    let mut current_dir = FileSystem::ROOT;
+
+    for TranscriptCommand {
+        line_number,
+        command,
+    } in commands
+    {
+        match command {
+            Command::Cd(path) => {
+                let created;
+                (current_dir, created) = change_directory(&mut filesystem, current_dir, path)
+                    .with_context(|| format!("Line {line_number}: Can't cd into '{path}'"))?;
+                if created {
+                    warnings.push(format!(
+                        "Line {line_number}: cd into '{}', which was never listed",
+                        filesystem.path(current_dir)
+                    ));
+                }
+            }
+            Command::Ls(entries) => {
+                let mut listing: BTreeMap<&str, &Entry> = BTreeMap::new();
+                for entry in entries {
+                    if listing.insert(entry.name(), entry).is_some() {
+                        bail!(
+                            "Line {line_number}: '{}' is listed twice in '{}'",
+                            entry.name(),
+                            filesystem.path(current_dir)
+                        );
+                    }
+                }
+
+                if let Some((first_line, first_listing)) = listings.get(&current_dir) {
+                    if *first_listing != listing {
+                        let name = first_listing
+                            .keys()
+                            .chain(listing.keys())
+                            .find(|name| first_listing.get(*name) != listing.get(*name))
+                            .expect("differing listings have a differing entry");
+                        bail!(
+                            "Line {line_number}: Listing of '{}' conflicts with the one on line {first_line} at '{name}'",
+                            filesystem.path(current_dir)
+                        );
+                    }
+                    continue;
+                }
+
+                for entry in entries {
+                    let existing = filesystem
+                        .child(current_dir, entry.name())
+                        .map(|child| filesystem.node(child).is_directory());
+                    match (entry, existing) {
+                        (Entry::Directory(name), None | Some(true)) => {
+                            filesystem.add_directory(current_dir, name);
+                        }
+                        (Entry::File { name, size }, None) => {
+                            filesystem.add_file(current_dir, name, *size);
+                        }
+                        (Entry::Directory(name), Some(false)) => {
+                            bail!("Line {line_number}: '{name}' is listed as a directory, but is a file")
+                        }
+                        (Entry::File { name, .. }, Some(_)) => bail!(
+                            "Line {line_number}: '{name}' is listed as a file, but was already visited as a directory"
+                        ),
+                    }
+                }
+                listings.insert(current_dir, (*line_number, listing));
+            }
+        }
+    }
+
+    for dir in filesystem.directories() {
+        if !listings.contains_key(&dir) {
+            warnings.push(format!(
+                "'{}' was never listed, its size may be incomplete",
+                filesystem.path(dir)
+            ));
+        }
+    }
+
+    Ok((filesystem, warnings))
+}
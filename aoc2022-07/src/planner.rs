@@ -0,0 +1,201 @@
+use std::str::FromStr;
+
+use anyhow::{bail, Result};
+
+use crate::filesystem::FileSystem;
+
+// the dp below keeps one bitset per directory, each as wide as the single directory answer
+const MAX_PLANNER_BYTES: usize = 1 << 30;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Planner {
+    // delete the smallest single directory that frees enough space
+    Single,
+    // delete the set of non-nested directories with the smallest total size
+    Multiple,
+}
+
+impl FromStr for Planner {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "single" => Ok(Planner::Single),
+            "multiple" => Ok(Planner::Multiple),
+            _ => bail!("Unknown planner '{s}', expected single or multiple"),
+        }
+    }
+}
+
+pub fn plan_single(filesystem: &FileSystem, required: u64) -> Option<usize> {
+    filesystem
+        .directories()
+        .filter(|dir| filesystem.size(*dir) >= required)
+        .min_by_key(|dir| filesystem.size(*dir))
+}
+
+struct BitSet {
+    words: Vec<u64>,
+}
+
+impl BitSet {
+    fn new(bits: usize) -> Self {
+        Self {
+            words: vec![0; bits.div_ceil(64)],
+        }
+    }
+
+    fn contains(&self, bit: usize) -> bool {
+        self.words
+            .get(bit / 64)
+            .is_some_and(|word| word & (1 << (bit % 64)) != 0)
+    }
+
+    fn insert(&mut self, bit: usize) {
+        if let Some(word) = self.words.get_mut(bit / 64) {
+            *word |= 1 << (bit % 64);
+        }
+    }
+
+    // self |= other << shift, dropping everything beyond the width
+    fn union_shifted(&mut self, other: &BitSet, shift: usize) {
+        let (word_shift, bit_shift) = (shift / 64, shift % 64);
+        for (idx, word) in other.words.iter().enumerate() {
+            if let Some(target) = self.words.get_mut(idx + word_shift) {
+                *target |= word << bit_shift;
+            }
+            if bit_shift > 0 {
+                if let Some(target) = self.words.get_mut(idx + word_shift + 1) {
+                    *target |= word >> (64 - bit_shift);
+                }
+            }
+        }
+    }
+}
+
+// directories in pre-order, each with the position right after its subtree
+fn preorder(filesystem: &FileSystem) -> Vec<(usize, usize)> {
+    let mut order: Vec<(usize, usize)> = Vec::new();
+    // (directory, index in order once visited)
+    let mut stack: Vec<(usize, Option<usize>)> = vec![(FileSystem::ROOT, None)];
+    while let Some((dir, visited)) = stack.pop() {
+        match visited {
+            Some(position) => order[position].1 = order.len(),
+            None => {
+                stack.push((dir, Some(order.len())));
+                order.push((dir, 0));
+                stack.extend(
+                    filesystem
+                        .children(dir)
+                        .filter(|child| filesystem.node(*child).is_directory())
+                        .map(|child| (child, None)),
+                );
+            }
+        }
+    }
+    order
+}
+
+// Taking a directory skips its whole subtree in pre-order, so picking non-nested directories is
+// a knapsack over pre-order positions. Only sums below the single directory answer matter.
+pub fn plan_multiple(filesystem: &FileSystem, required: u64) -> Result<Option<Vec<usize>>> {
+    let Some(single) = plan_single(filesystem, required) else {
+        return Ok(None);
+    };
+    let order = preorder(filesystem);
+    let width = filesystem.size(single) as usize;
+    if (order.len() + 1).saturating_mul(width / 8) > MAX_PLANNER_BYTES {
+        bail!(
+            "Planning for {width} bytes over {} directories would need too much memory",
+            order.len()
+        );
+    }
+
+    // reachable[i] holds the sums of deleted directories, once everything before position i is decided
+    let mut reachable = (0..=order.len())
+        .map(|_| BitSet::new(width))
+        .collect::<Vec<_>>();
+    reachable[0].insert(0);
+    for (position, (dir, end)) in order.iter().enumerate() {
+        let (before, after) = reachable.split_at_mut(position + 1);
+        let current = &before[position];
+        after[0].union_shifted(current, 0);
+        after[end - position - 1].union_shifted(current, filesystem.size(*dir) as usize);
+    }
+
+    let Some(mut sum) =
+        (required as usize..width).find(|sum| reachable[order.len()].contains(*sum))
+    else {
+        return Ok(Some(vec![single]));
+    };
+
+    let mut taken_until: Vec<Vec<usize>> = vec![Vec::new(); order.len() + 1];
+    for (start, (_, end)) in order.iter().enumerate() {
+        taken_until[*end].push(start);
+    }
+
+    // walk back through the positions, preferring to skip over taking a directory
+    let mut chosen: Vec<usize> = Vec::new();
+    let mut position = order.len();
+    while position > 0 {
+        if reachable[position - 1].contains(sum) {
+            position -= 1;
+            continue;
+        }
+        let start = *taken_until[position]
+            .iter()
+            .find(|start| {
+                let size = filesystem.size(order[**start].0) as usize;
+                size <= sum && reachable[**start].contains(sum - size)
+            })
+            .expect("every reachable sum has a predecessor");
+        let dir = order[start].0;
+        chosen.push(dir);
+        sum -= filesystem.size(dir) as usize;
+        position = start;
+    }
+    chosen.reverse();
+    Ok(Some(chosen))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{build_filesystem, parse_transcript};
+
+    fn filesystem(transcript: &str) -> FileSystem {
+        let commands = parse_transcript(transcript.lines().map(|line| Ok(line.to_string())))
+            .expect("valid transcript");
+        let (mut filesystem, _) = build_filesystem(&commands).expect("consistent transcript");
+        filesystem.compute_sizes();
+        filesystem
+    }
+
+    #[test]
+    fn multiple_matches_single_on_mock() {
+        let filesystem = filesystem(include_str!("../mock.txt"));
+        let required = 30000000 - (70000000 - filesystem.size(FileSystem::ROOT));
+        let single = plan_single(&filesystem, required);
+        assert_eq!(single, filesystem.lookup("/d"));
+        assert_eq!(
+            plan_multiple(&filesystem, required).expect("small enough to plan"),
+            single.map(|dir| vec![dir])
+        );
+    }
+
+    #[test]
+    fn multiple_prefers_two_siblings_over_their_parent() {
+        let filesystem = filesystem(
+            "$ cd /\n$ ls\ndir x\ndir y\ndir z\n$ cd x\n$ ls\n60 a\n$ cd ../y\n$ ls\n60 b\n$ cd ../z\n$ ls\n100 c\n",
+        );
+        assert_eq!(plan_single(&filesystem, 110), Some(FileSystem::ROOT));
+        let mut paths = plan_multiple(&filesystem, 110)
+            .expect("small enough to plan")
+            .expect("the root frees enough space")
+            .into_iter()
+            .map(|dir| filesystem.path(dir))
+            .collect::<Vec<_>>();
+        paths.sort();
+        assert_eq!(paths, vec!["/x", "/y"]);
+    }
+}
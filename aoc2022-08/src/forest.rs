@@ -0,0 +1,201 @@
+use std::{io, str::FromStr};
+
+use anyhow::{bail, Context, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Left,
+    Right,
+    Up,
+    Down,
+    UpLeft,
+    UpRight,
+    DownLeft,
+    DownRight,
+}
+
+impl Direction {
+    pub const ORTHOGONAL: [Direction; 4] = [
+        Direction::Left,
+        Direction::Right,
+        Direction::Up,
+        Direction::Down,
+    ];
+    pub const ALL: [Direction; 8] = [
+        Direction::Left,
+        Direction::Right,
+        Direction::Up,
+        Direction::Down,
+        Direction::UpLeft,
+        Direction::UpRight,
+        Direction::DownLeft,
+        Direction::DownRight,
+    ];
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Direction::Left => "L",
+            Direction::Right => "R",
+            Direction::Up => "U",
+            Direction::Down => "D",
+            Direction::UpLeft => "UL",
+            Direction::UpRight => "UR",
+            Direction::DownLeft => "DL",
+            Direction::DownRight => "DR",
+        }
+    }
+
+    // step when looking into this direction
+    pub fn offset(&self) -> (isize, isize) {
+        match self {
+            Direction::Left => (-1, 0),
+            Direction::Right => (1, 0),
+            Direction::Up => (0, -1),
+            Direction::Down => (0, 1),
+            Direction::UpLeft => (-1, -1),
+            Direction::UpRight => (1, -1),
+            Direction::DownLeft => (-1, 1),
+            Direction::DownRight => (1, 1),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Blocking {
+    // trees of the same height block the view, as in the puzzle
+    TallerOrEqual,
+    Taller,
+}
+
+impl FromStr for Blocking {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "taller-or-equal" => Ok(Blocking::TallerOrEqual),
+            "taller" => Ok(Blocking::Taller),
+            _ => bail!("Unknown blocking rule '{s}', expected taller-or-equal or taller"),
+        }
+    }
+}
+
+impl Blocking {
+    pub fn blocks(&self, other: u8, height: u8) -> bool {
+        match self {
+            Blocking::TallerOrEqual => other >= height,
+            Blocking::Taller => other > height,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Forest {
+    pub width: usize,
+    pub height: usize,
+    heights: Vec<u8>,
+}
+
+// what a single direction looks like from each tree
+#[derive(Debug)]
+pub struct SightLine {
+    pub direction: Direction,
+    // no blocking tree is in the way to the edge
+    pub visible: Vec<bool>,
+    // number of trees up to and including the first blocking tree, or up to the edge
+    pub distance: Vec<u32>,
+}
+
+impl Forest {
+    // every row has to have the same number of trees, empty lines are skipped
+    pub fn parse(lines: impl Iterator<Item = io::Result<String>>) -> Result<Self> {
+        let mut width: Option<(usize, usize)> = None;
+        let mut height = 0;
+        let mut heights: Vec<u8> = Vec::new();
+        for (idx, line) in lines.enumerate() {
+            let line_number = idx + 1;
+            let line = line.with_context(|| format!("Line {line_number}: Failed to read line"))?;
+            let line = line.trim_end();
+            if line.is_empty() {
+                continue;
+            }
+
+            let row = line
+                .chars()
+                .map(|c| match c.to_digit(10) {
+                    Some(digit) => Ok(digit as u8),
+                    None => bail!("Line {line_number}: '{c}' is not a tree height"),
+                })
+                .collect::<Result<Vec<u8>>>()?;
+            match width {
+                Some((width, first_line)) if width != row.len() => bail!(
+                    "Line {line_number}: Row has {} trees, but line {first_line} has {width}",
+                    row.len()
+                ),
+                Some(_) => {}
+                None => width = Some((row.len(), line_number)),
+            }
+            heights.extend(row);
+            height += 1;
+        }
+
+        Ok(Self {
+            width: width.map(|(width, _)| width).unwrap_or(0),
+            height,
+            heights,
+        })
+    }
+
+    pub fn height_at(&self, x: usize, y: usize) -> u8 {
+        self.heights[y * self.width + x]
+    }
+
+    fn step(&self, (x, y): (usize, usize), (dx, dy): (isize, isize)) -> Option<(usize, usize)> {
+        let x = x.checked_add_signed(dx).filter(|x| *x < self.width)?;
+        let y = y.checked_add_signed(dy).filter(|y| *y < self.height)?;
+        Some((x, y))
+    }
+
+    // Walks every line against the viewing direction, starting at the edge the trees look at.
+    // The stack keeps the trees already passed that are not hidden behind a blocking one,
+    // so every tree is pushed and popped at most once.
+    pub fn sight_line(&self, direction: Direction, blocking: Blocking) -> SightLine {
+        let (dx, dy) = direction.offset();
+        let mut visible = vec![false; self.heights.len()];
+        let mut distance = vec![0; self.heights.len()];
+        let mut stack: Vec<((usize, usize), u32)> = Vec::new();
+
+        let starts = (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .filter(|cell| self.step(*cell, (dx, dy)).is_none());
+        for start in starts {
+            stack.clear();
+            let mut cell = Some(start);
+            let mut steps: u32 = 0;
+            while let Some((x, y)) = cell {
+                let height = self.height_at(x, y);
+                while stack.last().is_some_and(|((other_x, other_y), _)| {
+                    !blocking.blocks(self.height_at(*other_x, *other_y), height)
+                }) {
+                    stack.pop();
+                }
+                let idx = y * self.width + x;
+                match stack.last() {
+                    Some((_, other_steps)) => distance[idx] = steps - other_steps,
+                    None => {
+                        visible[idx] = true;
+                        distance[idx] = steps;
+                    }
+                }
+                stack.push(((x, y), steps));
+                steps += 1;
+                cell = self.step((x, y), (-dx, -dy));
+            }
+        }
+
+        SightLine {
+            direction,
+            visible,
+            distance,
+        }
+    }
+}
@@ -0,0 +1,170 @@
+use std::{fmt::Write, str::FromStr};
+
+use anyhow::{bail, Context};
+
+use crate::forest::{Forest, SightLine};
+
+const COLOUR_RESET: &str = "\x1b[0m";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapKind {
+    // from how many directions a tree can be seen
+    Visibility,
+    Scenic,
+}
+
+impl FromStr for MapKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "visibility" => Ok(MapKind::Visibility),
+            "scenic" => Ok(MapKind::Scenic),
+            _ => bail!("Unknown map '{s}', expected visibility or scenic"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Pgm,
+    Ppm,
+    Csv,
+}
+
+impl ExportFormat {
+    pub fn from_file_name(file_name: &str) -> Result<Self, anyhow::Error> {
+        let extension = file_name
+            .rsplit_once('.')
+            .map(|(_, extension)| extension.to_ascii_lowercase())
+            .with_context(|| format!("Can't tell the export format of '{file_name}'"))?;
+        match extension.as_str() {
+            "pgm" => Ok(ExportFormat::Pgm),
+            "ppm" => Ok(ExportFormat::Ppm),
+            "csv" => Ok(ExportFormat::Csv),
+            _ => bail!("Unknown export format '.{extension}', expected .pgm, .ppm or .csv"),
+        }
+    }
+}
+
+// blue over green and yellow to red, for an intensity between 0 and 1
+fn heat_colour(intensity: f64) -> (u8, u8, u8) {
+    const STOPS: [(f64, f64, f64); 4] = [
+        (0.0, 0.0, 128.0),
+        (0.0, 160.0, 0.0),
+        (255.0, 220.0, 0.0),
+        (220.0, 0.0, 0.0),
+    ];
+    let scaled = intensity.clamp(0.0, 1.0) * (STOPS.len() - 1) as f64;
+    let idx = (scaled.floor() as usize).min(STOPS.len() - 2);
+    let t = scaled - idx as f64;
+    let (from, to) = (STOPS[idx], STOPS[idx + 1]);
+    let mix = |a: f64, b: f64| (a + (b - a) * t).round() as u8;
+    (mix(from.0, to.0), mix(from.1, to.1), mix(from.2, to.2))
+}
+
+#[derive(Debug)]
+pub struct Heatmap {
+    width: usize,
+    height: usize,
+    // between 0 and 1, used for the colours and grey levels
+    intensity: Vec<f64>,
+    // the exact value of each cell, used for the CSV export
+    labels: Vec<String>,
+}
+
+impl Heatmap {
+    pub fn visibility(forest: &Forest, sight_lines: &[SightLine]) -> Self {
+        let cells = forest.width * forest.height;
+        let labels = (0..cells)
+            .map(|idx| {
+                let directions = sight_lines
+                    .iter()
+                    .filter(|sight| sight.visible[idx])
+                    .map(|sight| sight.direction.symbol())
+                    .collect::<Vec<_>>();
+                if directions.is_empty() {
+                    "-".to_string()
+                } else {
+                    directions.join(" ")
+                }
+            })
+            .collect();
+        let intensity = (0..cells)
+            .map(|idx| {
+                let visible = sight_lines
+                    .iter()
+                    .filter(|sight| sight.visible[idx])
+                    .count();
+                visible as f64 / sight_lines.len().max(1) as f64
+            })
+            .collect();
+        Self {
+            width: forest.width,
+            height: forest.height,
+            intensity,
+            labels,
+        }
+    }
+
+    // scores are spread over several orders of magnitude, so the intensity is logarithmic
+    pub fn scenic(forest: &Forest, scores: &[u128]) -> Self {
+        let max = scores.iter().copied().max().unwrap_or(0);
+        let scale = (max as f64 + 1.0).ln().max(f64::MIN_POSITIVE);
+        Self {
+            width: forest.width,
+            height: forest.height,
+            intensity: scores
+                .iter()
+                .map(|score| (*score as f64 + 1.0).ln() / scale)
+                .collect(),
+            labels: scores.iter().map(|score| score.to_string()).collect(),
+        }
+    }
+
+    // tree heights on a coloured background
+    pub fn render(&self, forest: &Forest) -> String {
+        let mut output = String::new();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let (r, g, b) = heat_colour(self.intensity[y * self.width + x]);
+                let _ = write!(
+                    output,
+                    "\x1b[48;2;{r};{g};{b}m\x1b[97m{}",
+                    forest.height_at(x, y)
+                );
+            }
+            output.push_str(COLOUR_RESET);
+            output.push('\n');
+        }
+        output
+    }
+
+    pub fn export(&self, format: ExportFormat) -> Vec<u8> {
+        match format {
+            ExportFormat::Pgm => {
+                let mut image = format!("P5\n{} {}\n255\n", self.width, self.height).into_bytes();
+                image.extend(
+                    self.intensity
+                        .iter()
+                        .map(|intensity| (intensity.clamp(0.0, 1.0) * 255.0).round() as u8),
+                );
+                image
+            }
+            ExportFormat::Ppm => {
+                let mut image = format!("P6\n{} {}\n255\n", self.width, self.height).into_bytes();
+                for intensity in self.intensity.iter() {
+                    let (r, g, b) = heat_colour(*intensity);
+                    image.extend([r, g, b]);
+                }
+                image
+            }
+            ExportFormat::Csv => self
+                .labels
+                .chunks(self.width.max(1))
+                .map(|row| format!("{}\n", row.join(",")))
+                .collect::<String>()
+                .into_bytes(),
+        }
+    }
+}
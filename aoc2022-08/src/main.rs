@@ -1,91 +1,132 @@
 use std::{
     error::Error,
-    fs::File,
+    fs::{write, File},
     io::{BufRead, BufReader},
 };
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let file_name = std::env::args().nth(1).expect("No input file supplied!");
-    let lines = BufReader::new(File::open(file_name)?)
-        .lines()
-        .into_iter()
-        .filter_map(|line| line.ok());
+use anyhow::{bail, Context};
+use forest::{Blocking, Direction, Forest};
+use heatmap::{ExportFormat, Heatmap, MapKind};
 
-    let grid: Vec<Vec<u32>> = lines
-        .map(|line| {
-            line.chars()
-                .filter_map(|c| c.to_digit(10))
-                .collect::<Vec<u32>>()
-        })
-        .collect();
+mod forest;
+mod heatmap;
 
-    let mut visible_trees: u32 = 0;
-    let mut highest_scenic_score: u32 = 0;
-    let mut scenic_score_position: (usize, usize) = (0, 0);
-    for (y, row) in grid.iter().enumerate() {
-        for (x, tree) in row.iter().enumerate() {
-            // check if tree is on the edge
-            // if x == 0 || y == 0 || x == row.len() - 1 || y == grid.len() - 1 {
-            //     visible_trees += 1;
-            //     continue;
-            // }
+#[derive(Debug)]
+struct Options {
+    file_name: String,
+    // print the view distances of every single tree
+    verbose: bool,
+    // 4 orthogonal or all 8 directions, including diagonals
+    directions: usize,
+    blocking: Blocking,
+    map: MapKind,
+    // print the map to the terminal
+    heatmap: bool,
+    // .pgm, .ppm or .csv file to write the map to
+    export: Option<String>,
+}
 
-            // check if any other tree is higher or has the same hight as the current tree
-            let mut visible_from_left = true;
-            let mut view_distance_left: u32 = u32::MAX;
-            let mut visible_from_right = true;
-            let mut view_distance_right: u32 = u32::MAX;
-            for (other_x, other_tree) in row.iter().enumerate() {
-                if other_x < x && other_tree >= tree {
-                    visible_from_left = false;
-                    view_distance_left = u32::min(view_distance_left, (x - other_x) as u32);
-                }
-                if other_x > x && other_tree >= tree {
-                    visible_from_right = false;
-                    view_distance_right = u32::min(view_distance_right, (other_x - x) as u32);
-                }
-            }
-            // tree is on edge
-            if view_distance_left == u32::MAX {
-                view_distance_left = x as u32;
-            }
-            if view_distance_right == u32::MAX {
-                view_distance_right = (row.len() - x) as u32 - 1;
-            }
+impl Options {
+    fn from_args() -> Result<Self, anyhow::Error> {
+        let mut file_name: Option<String> = None;
+        let mut verbose = false;
+        let mut directions: usize = 4;
+        let mut blocking = Blocking::TallerOrEqual;
+        let mut map = MapKind::Scenic;
+        let mut heatmap = false;
+        let mut export: Option<String> = None;
 
-            let mut visible_from_top = true;
-            let mut view_distance_top: u32 = u32::MAX;
-            let mut visible_from_bottom = true;
-            let mut view_distance_bottom: u32 = u32::MAX;
-            for (other_y, other_tree) in
-                grid.iter().enumerate().filter_map(|(other_y, other_row)| {
-                    other_row.get(x).map(|other_tree| (other_y, other_tree))
-                })
-            {
-                if other_y < y && other_tree >= tree {
-                    visible_from_top = false;
-                    view_distance_top = u32::min(view_distance_top, (y - other_y) as u32);
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--verbose" | "-v" => verbose = true,
+                "--directions" => {
+                    directions = match args.next().as_deref() {
+                        Some("4") => 4,
+                        Some("8") => 8,
+                        _ => bail!("--directions requires 4 or 8"),
+                    }
                 }
-                if other_y > y && other_tree >= tree {
-                    visible_from_bottom = false;
-                    view_distance_bottom = u32::min(view_distance_bottom, (other_y - y) as u32);
+                "--blocking" => {
+                    blocking = args
+                        .next()
+                        .context("--blocking requires taller-or-equal or taller")?
+                        .parse()?
                 }
+                "--map" => {
+                    map = args
+                        .next()
+                        .context("--map requires visibility or scenic")?
+                        .parse()?
+                }
+                "--heatmap" => heatmap = true,
+                "--export" => export = Some(args.next().context("--export requires a file")?),
+                flag if flag.starts_with("--") => bail!("Unknown option '{flag}'"),
+                _ if file_name.is_none() => file_name = Some(arg),
+                _ => bail!("Unexpected argument '{arg}'"),
             }
-            // tree is on edge
-            if view_distance_top == u32::MAX {
-                view_distance_top = y as u32;
-            }
-            if view_distance_bottom == u32::MAX {
-                view_distance_bottom = (grid.len() - y) as u32 - 1;
-            }
+        }
 
-            if visible_from_left || visible_from_right || visible_from_top || visible_from_bottom {
+        Ok(Self {
+            file_name: file_name.context("No input file supplied!")?,
+            verbose,
+            directions,
+            blocking,
+            map,
+            heatmap,
+            export,
+        })
+    }
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let options = Options::from_args()?;
+    let lines = BufReader::new(File::open(&options.file_name)?).lines();
+
+    let forest = Forest::parse(lines)?;
+    let directions: &[Direction] = match options.directions {
+        8 => &Direction::ALL,
+        _ => &Direction::ORTHOGONAL,
+    };
+    let sight_lines = directions
+        .iter()
+        .map(|direction| forest.sight_line(*direction, options.blocking))
+        .collect::<Vec<_>>();
+
+    let export_format = options
+        .export
+        .as_deref()
+        .map(ExportFormat::from_file_name)
+        .transpose()?;
+
+    let mut scores: Vec<u128> = Vec::with_capacity(forest.width * forest.height);
+    let mut visible_trees: u32 = 0;
+    let mut highest_scenic_score: u128 = 0;
+    let mut scenic_score_position: (usize, usize) = (0, 0);
+    for y in 0..forest.height {
+        for x in 0..forest.width {
+            let idx = y * forest.width + x;
+            if sight_lines.iter().any(|sight| sight.visible[idx]) {
                 visible_trees += 1;
             }
-            let scenic_score =
-                view_distance_left * view_distance_right * view_distance_top * view_distance_bottom;
-            println!("[{tree}] {x}x{y} => {scenic_score} ({view_distance_left} * {view_distance_right} * {view_distance_top} * {view_distance_bottom})");
+            // eight long view distances don't fit into a u64, so the score saturates instead
+            let scenic_score = sight_lines
+                .iter()
+                .map(|sight| sight.distance[idx] as u128)
+                .fold(1, u128::saturating_mul);
+            if options.verbose {
+                let distances = sight_lines
+                    .iter()
+                    .map(|sight| sight.distance[idx].to_string())
+                    .collect::<Vec<_>>()
+                    .join(" * ");
+                println!(
+                    "[{}] {x}x{y} => {scenic_score} ({distances})",
+                    forest.height_at(x, y)
+                );
+            }
 
+            scores.push(scenic_score);
             if scenic_score > highest_scenic_score {
                 highest_scenic_score = scenic_score;
                 scenic_score_position = (x, y);
@@ -95,5 +136,19 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     println!("visible_trees: {visible_trees}");
     println!("highest scenic score: {highest_scenic_score} {scenic_score_position:?}");
+
+    if options.heatmap || options.export.is_some() {
+        let heatmap = match options.map {
+            MapKind::Visibility => Heatmap::visibility(&forest, &sight_lines),
+            MapKind::Scenic => Heatmap::scenic(&forest, &scores),
+        };
+        if options.heatmap {
+            print!("{}", heatmap.render(&forest));
+        }
+        if let (Some(file_name), Some(format)) = (options.export.as_deref(), export_format) {
+            write(file_name, heatmap.export(format))
+                .with_context(|| format!("Failed to write the map to '{file_name}'"))?;
+        }
+    }
     Ok(())
 }
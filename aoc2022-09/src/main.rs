@@ -1,13 +1,20 @@
 use std::{
     collections::HashSet,
     error::Error,
+    fmt::Display,
     fs::File,
     hash::Hash,
     io::{BufRead, BufReader},
-    ops::AddAssign,
     str::FromStr,
+    thread::sleep,
+    time::Duration,
 };
 
+use anyhow::{bail, Context};
+use render::{render_rope, render_visited};
+
+mod render;
+
 #[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
 struct Position2D {
     x: i64,
@@ -18,7 +25,7 @@ impl Position2D {
     fn is_adjacent_to(&self, other: &Self) -> bool {
         let dx = self.x - other.x;
         let dy = self.y - other.y;
-        dx >= -1 && dx <= 1 && dy >= -1 && dy <= 1
+        (-1..=1).contains(&dx) && (-1..=1).contains(&dy)
     }
 
     fn apply_step_with_length_one(&mut self, step: &Step) {
@@ -30,79 +37,11 @@ impl Position2D {
         }
     }
 
-    fn follow_other(&mut self, other: &Self, step: &Step) {
-        match (
-            step,
-            (self.x - other.x).clamp(-1, 1),
-            (self.y - other.y).clamp(-1, 1),
-        ) {
-            (_, 0, 0) => {}
-            // up
-            (_, 0, -1) => {
-                // self.x = other.x;
-                self.y = other.y - 1;
-            }
-            // down
-            (_, 0, 1) => {
-                // self.x = other.x;
-                self.y = other.y + 1;
-            }
-            // left
-            (_, -1, 0) => {
-                self.x = other.x - 1;
-                // self.y = other.y;
-            }
-            // right
-            (_, 1, 0) => {
-                self.x = other.x + 1;
-            }
-            // bottom right
-            (_, 1, 1) => {
-                self.x = other.x - 1;
-                self.y = other.y;
-            }
-            // top right
-            (_, 1, -1) => {}
-
-            _ => panic!(),
-        }
-    }
-}
-
-// impl Add<&Step> for Position2D {
-//     type Output = Self;
-
-//     fn add(self, rhs: &Step) -> Self::Output {
-//         match rhs {
-//             Step::Up(length) => Self {
-//                 x: self.x,
-//                 y: self.y - (*length as i64),
-//             },
-//             Step::Down(length) => Self {
-//                 x: self.x,
-//                 y: self.y + (*length as i64),
-//             },
-//             Step::Left(length) => Self {
-//                 x: self.x - (*length as i64),
-//                 y: self.y,
-//             },
-//             Step::Right(length) => Self {
-//                 x: self.x + (*length as i64),
-//                 y: self.y,
-//             },
-//         };
-
-//         self
-//     }
-// }
-
-impl AddAssign<&Step> for Position2D {
-    fn add_assign(&mut self, rhs: &Step) {
-        match rhs {
-            Step::Up(length) => self.y -= *length as i64,
-            Step::Down(length) => self.y += *length as i64,
-            Step::Left(length) => self.x -= *length as i64,
-            Step::Right(length) => self.x += *length as i64,
+    // moves at most one step on each axis towards the other knot, once it is no longer adjacent
+    fn follow(&mut self, other: &Self) {
+        if !self.is_adjacent_to(other) {
+            self.x += (other.x - self.x).signum();
+            self.y += (other.y - self.y).signum();
         }
     }
 }
@@ -126,6 +65,18 @@ impl Step {
     }
 }
 
+impl Display for Step {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let direction = match self {
+            Self::Up(_) => "U",
+            Self::Down(_) => "D",
+            Self::Left(_) => "L",
+            Self::Right(_) => "R",
+        };
+        write!(f, "{direction} {}", self.get_length())
+    }
+}
+
 impl FromStr for Step {
     type Err = anyhow::Error;
 
@@ -141,64 +92,220 @@ impl FromStr for Step {
     }
 }
 
-fn shift_knot_buffer(knots: &mut [Position2D]) {
-    for idx in 1..knots.len() {
-        knots[idx - 1].x = knots[idx].x;
-        knots[idx - 1].y = knots[idx].y;
+#[derive(Debug, Clone)]
+struct Rope {
+    // the head comes first
+    knots: Vec<Position2D>,
+    // index of the knot whose positions are tracked
+    tracked: usize,
+    visited: HashSet<Position2D>,
+}
+
+impl Rope {
+    fn new(length: usize, tracked: usize) -> Self {
+        let knots = vec![Position2D::default(); length];
+        let visited = HashSet::from([knots[tracked].clone()]);
+        Self {
+            knots,
+            tracked,
+            visited,
+        }
+    }
+
+    // moves the head by a single field and lets every other knot follow its predecessor
+    fn step_once(&mut self, step: &Step) {
+        self.knots[0].apply_step_with_length_one(step);
+        for idx in 1..self.knots.len() {
+            let (leading, following) = self.knots.split_at_mut(idx);
+            following[0].follow(&leading[idx - 1]);
+        }
+        self.visited.insert(self.knots[self.tracked].clone());
+    }
+
+    fn apply(&mut self, step: &Step) {
+        for _ in 0..step.get_length() {
+            self.step_once(step);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Animation {
+    // one frame after each line of the input
+    Move,
+    // one frame after each single field the head moves
+    Step,
+}
+
+impl FromStr for Animation {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "move" => Ok(Animation::Move),
+            "step" => Ok(Animation::Step),
+            _ => bail!("Unknown animation '{s}', expected move or step"),
+        }
     }
 }
 
-fn add_knots_to_tracker(positions: &mut HashSet<Position2D>, knots: &[Position2D]) {
-    for knot in knots.iter() {
-        positions.insert(knot.clone());
+#[derive(Debug)]
+struct Options {
+    file_name: String,
+    // number of knots for each simulated rope
+    lengths: Vec<usize>,
+    // knot to track, the tail by default
+    tracked: Option<usize>,
+    animate: Option<Animation>,
+    delay: Duration,
+    // width and height of the animation window
+    viewport: (i64, i64),
+    // print all cells visited by the tracked knot at the end
+    map: bool,
+}
+
+impl Options {
+    fn from_args() -> Result<Self, anyhow::Error> {
+        let mut file_name: Option<String> = None;
+        let mut lengths: Vec<usize> = Vec::new();
+        let mut tracked: Option<usize> = None;
+        let mut animate: Option<Animation> = None;
+        let mut delay = Duration::from_millis(250);
+        let mut viewport: (i64, i64) = (40, 20);
+        let mut map = false;
+
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--knots" => lengths.push(
+                    args.next()
+                        .context("--knots requires a number")?
+                        .parse()
+                        .context("--knots requires a number")?,
+                ),
+                "--track" => {
+                    tracked = Some(
+                        args.next()
+                            .context("--track requires a knot index")?
+                            .parse()
+                            .context("--track requires a knot index")?,
+                    )
+                }
+                "--animate" => {
+                    animate = Some(
+                        args.next()
+                            .context("--animate requires move or step")?
+                            .parse()?,
+                    )
+                }
+                "--delay" => {
+                    delay = Duration::from_millis(
+                        args.next()
+                            .context("--delay requires milliseconds")?
+                            .parse()
+                            .context("--delay requires milliseconds")?,
+                    )
+                }
+                "--viewport" => {
+                    let size = args.next().context("--viewport requires WIDTHxHEIGHT")?;
+                    viewport = size
+                        .split_once('x')
+                        .and_then(|(width, height)| {
+                            Some((width.parse().ok()?, height.parse().ok()?))
+                        })
+                        .filter(|(width, height)| *width > 0 && *height > 0)
+                        .with_context(|| format!("'{size}' is not a viewport size like 40x20"))?;
+                }
+                "--map" => map = true,
+                flag if flag.starts_with("--") => bail!("Unknown option '{flag}'"),
+                _ if file_name.is_none() => file_name = Some(arg),
+                _ => bail!("Unexpected argument '{arg}'"),
+            }
+        }
+
+        if lengths.is_empty() {
+            lengths = vec![2, 10];
+        }
+        if let Some(length) = lengths.iter().find(|length| **length == 0) {
+            bail!("A rope needs at least one knot, got {length}");
+        }
+        if let Some(tracked) = tracked {
+            if let Some(length) = lengths.iter().find(|length| **length <= tracked) {
+                bail!("Can't track knot {tracked} on a rope with {length} knots");
+            }
+        }
+
+        Ok(Self {
+            file_name: file_name.context("No input file supplied!")?,
+            lengths,
+            tracked,
+            animate,
+            delay,
+            viewport,
+            map,
+        })
     }
 }
 
+fn draw_frame(rope: &Rope, options: &Options, idx: usize, total: usize, step: &Step) {
+    // clear the terminal and move the cursor to the top left
+    print!("\x1b[2J\x1b[H");
+    println!(
+        "{} knots - move {}/{total}: {step}",
+        rope.knots.len(),
+        idx + 1
+    );
+    println!();
+    print!(
+        "{}",
+        render_rope(rope, options.viewport.0, options.viewport.1)
+    );
+    sleep(options.delay);
+}
+
 fn main() -> Result<(), Box<dyn Error>> {
-    let file_name = std::env::args().nth(1).expect("No input file supplied!");
-    let steps = BufReader::new(File::open(file_name)?)
+    let options = Options::from_args()?;
+    let steps = BufReader::new(File::open(&options.file_name)?)
         .lines()
-        .into_iter()
-        .filter_map(|line| line.ok())
-        .map(|line| Step::from_str(&line))
+        .enumerate()
+        .map(|(idx, line)| {
+            let line = line.with_context(|| format!("Line {}: Failed to read line", idx + 1))?;
+            if line.trim().is_empty() {
+                return Ok(None);
+            }
+            Step::from_str(line.trim_end())
+                .map(Some)
+                .with_context(|| format!("Line {}: Invalid step '{line}'", idx + 1))
+        })
+        .filter_map(Result::transpose)
         .collect::<Result<Vec<_>, _>>()?;
 
-    let mut tail_positions = HashSet::<Position2D>::new();
-    let mut head = Position2D::default();
-    let mut knots: [Position2D; 11] = [
-        head.clone(),
-        head.clone(),
-        head.clone(),
-        head.clone(),
-        head.clone(),
-        head.clone(),
-        head.clone(),
-        head.clone(),
-        head.clone(),
-        head.clone(),
-        head.clone(),
-    ];
-    // let mut tail = head.clone();
-    add_knots_to_tracker(&mut tail_positions, &knots);
-
-    for step in steps.iter() {
-        println!("==== {step:?} ====");
-        for _ in 0..step.get_length() {
-            let prev_head = head.clone();
-            head.apply_step_with_length_one(step);
-            if !head.is_adjacent_to(&knots[knots.len() - 1]) {
-                shift_knot_buffer(&mut knots);
-                knots[knots.len() - 1] = prev_head;
-                add_knots_to_tracker(&mut tail_positions, &knots);
-                // tail_positions.insert(tail.clone());
-                print!("-> ");
+    for length in options.lengths.iter() {
+        let mut rope = Rope::new(*length, options.tracked.unwrap_or(length - 1));
+        for (idx, step) in steps.iter().enumerate() {
+            match options.animate {
+                Some(Animation::Move) => {
+                    rope.apply(step);
+                    draw_frame(&rope, &options, idx, steps.len(), step);
+                }
+                Some(Animation::Step) => {
+                    for _ in 0..step.get_length() {
+                        rope.step_once(step);
+                        draw_frame(&rope, &options, idx, steps.len(), step);
+                    }
+                }
+                None => rope.apply(step),
             }
-            println!("head: {head:?}   | knots: {knots:?}");
+        }
+        println!(
+            "Rope with {length} knots => positions visited by knot {}: {}",
+            rope.tracked,
+            rope.visited.len()
+        );
+        if options.map {
+            println!("{}", render_visited(&rope.visited));
         }
     }
 
-    // println!("{tail_positions:?}");
-    println!("Number of tail positions: {}", tail_positions.len());
-
     Ok(())
 }
@@ -0,0 +1,60 @@
+use std::collections::HashSet;
+
+use crate::{Position2D, Rope};
+
+// `H` for the head like in the puzzle, knot indices as digits and `s` for the start
+fn knot_symbol(idx: usize) -> char {
+    match idx {
+        0 => 'H',
+        1..=9 => char::from_digit(idx as u32, 10).unwrap_or('#'),
+        _ => '#',
+    }
+}
+
+// a `width` x `height` window centred on the head
+pub fn render_rope(rope: &Rope, width: i64, height: i64) -> String {
+    let head = &rope.knots[0];
+    let (left, top) = (head.x - width / 2, head.y - height / 2);
+    let mut output = String::new();
+    for y in top..top + height {
+        for x in left..left + width {
+            let position = Position2D { x, y };
+            let symbol = match rope.knots.iter().position(|knot| *knot == position) {
+                Some(idx) => knot_symbol(idx),
+                None if position == Position2D::default() => 's',
+                None => '.',
+            };
+            output.push(symbol);
+        }
+        output.push('\n');
+    }
+    output
+}
+
+// every visited cell inside the bounding box of all visited cells
+pub fn render_visited(visited: &HashSet<Position2D>) -> String {
+    let (Some(min_x), Some(max_x), Some(min_y), Some(max_y)) = (
+        visited.iter().map(|position| position.x).min(),
+        visited.iter().map(|position| position.x).max(),
+        visited.iter().map(|position| position.y).min(),
+        visited.iter().map(|position| position.y).max(),
+    ) else {
+        return String::new();
+    };
+
+    let mut output = String::new();
+    for y in min_y..=max_y {
+        for x in min_x..=max_x {
+            let position = Position2D { x, y };
+            output.push(if position == Position2D::default() {
+                's'
+            } else if visited.contains(&position) {
+                '#'
+            } else {
+                '.'
+            });
+        }
+        output.push('\n');
+    }
+    output
+}